@@ -0,0 +1,330 @@
+//! Authentication challenges sent by servers in `WWW-Authenticate`.
+
+use crate::header::is_tchar;
+
+/// The first challenge of a `WWW-Authenticate` header, like `Digest realm="cam", nonce="abc"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthChallenge<'a> {
+    /// The auth scheme, like `Basic`, `Bearer` or `Digest`
+    pub scheme: &'a str,
+    /// Everything after the scheme, for schemes with parameters not covered by [`AuthChallenge::param`]
+    pub rest: &'a str,
+}
+
+impl<'a> AuthChallenge<'a> {
+    /// Parses a challenge, returns `None` if it doesn't start with a scheme
+    pub fn parse(s: &'a str) -> Option<Self> {
+        let s = s.trim_start_matches([' ', '\t']);
+        let end = s.bytes().position(|b| !is_tchar(b)).unwrap_or(s.len());
+        if end == 0 || s[end..].starts_with(|c| c != ' ' && c != '\t') {
+            return None;
+        }
+
+        Some(Self {
+            scheme: &s[..end],
+            rest: s[end..].trim_matches([' ', '\t']),
+        })
+    }
+
+    /// The `realm` parameter
+    pub fn realm(&self) -> Option<&'a str> {
+        self.param("realm")
+    }
+
+    /// Value of a parameter, with the name compared case-insensitively.
+    /// Quoted values are returned without the quotes, escapes are left as they are.
+    pub fn param(&self, name: &str) -> Option<&'a str> {
+        self.params()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Iterates over the `name=value` parameters of the challenge
+    pub fn params(&self) -> Params<'a> {
+        Params { rest: self.rest }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AuthChallenge<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str} {=str}", self.scheme, self.rest);
+    }
+}
+
+/// Iterator over the parameters of an [`AuthChallenge`]
+pub struct Params<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Params<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.trim_start_matches([' ', '\t', ',']);
+
+        // stops at a token68 or at the scheme of a following challenge
+        let (name, value) = rest.split_once('=')?;
+        let name = name.trim_end_matches([' ', '\t']);
+        if name.is_empty() || !name.bytes().all(is_tchar) {
+            return None;
+        }
+        let value = value.trim_start_matches([' ', '\t']);
+
+        let (value, rest) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let mut escaped = false;
+                let end = quoted.char_indices().find_map(|(idx, c)| match c {
+                    _ if escaped => {
+                        escaped = false;
+                        None
+                    }
+                    '\\' => {
+                        escaped = true;
+                        None
+                    }
+                    '"' => Some(idx),
+                    _ => None,
+                })?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => {
+                let end = value.find([',', ' ', '\t']).unwrap_or(value.len());
+                value.split_at(end)
+            }
+        };
+
+        self.rest = rest;
+        Some((name, value))
+    }
+}
+
+/// Answers a `Digest` challenge (RFC 2617) with the `Authorization` header.
+/// Supports `qop=auth` and challenges without `qop`, using MD5.
+#[cfg(feature = "digest-auth")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigestAuth<'a> {
+    pub username: &'a str,
+    pub password: &'a str,
+    pub realm: &'a str,
+    pub nonce: &'a str,
+    pub opaque: Option<&'a str>,
+    /// whether the challenge offered `qop=auth`
+    pub qop_auth: bool,
+}
+
+#[cfg(feature = "digest-auth")]
+impl<'a> DigestAuth<'a> {
+    /// Returns `None` if the challenge isn't a `Digest` challenge with a realm and nonce
+    /// that can be answered using MD5, and either no `qop` or `qop=auth`
+    pub fn new(
+        challenge: &AuthChallenge<'a>,
+        username: &'a str,
+        password: &'a str,
+    ) -> Option<Self> {
+        if !challenge.scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        if !challenge
+            .param("algorithm")
+            .is_none_or(|a| a.eq_ignore_ascii_case("md5"))
+        {
+            return None;
+        }
+
+        let qop_auth = match challenge.param("qop") {
+            None => false,
+            Some(qop) if qop.split(',').any(|q| q.trim() == "auth") => true,
+            Some(_) => return None,
+        };
+
+        Some(Self {
+            username,
+            password,
+            realm: challenge.realm()?,
+            nonce: challenge.param("nonce")?,
+            opaque: challenge.param("opaque"),
+            qop_auth,
+        })
+    }
+
+    /// Computes the hex encoded `response` for a request.
+    /// `cnonce` and the nonce count `nc` are only used with `qop=auth`.
+    pub fn response(
+        &self,
+        method: crate::request::Method,
+        uri: &str,
+        cnonce: &str,
+        nc: u32,
+    ) -> [u8; 32] {
+        let ha1 = md5_hex(&[self.username, self.realm, self.password]);
+        let ha2 = md5_hex(&[method.str(), uri]);
+        let ha1 = core::str::from_utf8(&ha1).unwrap_or_default();
+        let ha2 = core::str::from_utf8(&ha2).unwrap_or_default();
+
+        if self.qop_auth {
+            let nc = nc_hex(nc);
+            let nc = core::str::from_utf8(&nc).unwrap_or_default();
+            md5_hex(&[ha1, self.nonce, nc, cnonce, "auth", ha2])
+        } else {
+            md5_hex(&[ha1, self.nonce, ha2])
+        }
+    }
+
+    /// Formats the `Authorization` header value answering the challenge
+    pub fn authorization(
+        &self,
+        method: crate::request::Method,
+        uri: &str,
+        cnonce: &str,
+        nc: u32,
+    ) -> crate::header::HeaderValue<'static> {
+        let response = self.response(method, uri, cnonce, nc);
+
+        let mut value = alloc::string::String::from("Digest");
+        push_param(&mut value, "username", self.username, true);
+        push_param(&mut value, "realm", self.realm, true);
+        push_param(&mut value, "nonce", self.nonce, true);
+        push_param(&mut value, "uri", uri, true);
+        if self.qop_auth {
+            let nc = nc_hex(nc);
+            push_param(&mut value, "qop", "auth", false);
+            push_param(
+                &mut value,
+                "nc",
+                core::str::from_utf8(&nc).unwrap_or_default(),
+                false,
+            );
+            push_param(&mut value, "cnonce", cnonce, true);
+        }
+        push_param(
+            &mut value,
+            "response",
+            core::str::from_utf8(&response).unwrap_or_default(),
+            true,
+        );
+        if let Some(opaque) = self.opaque {
+            push_param(&mut value, "opaque", opaque, true);
+        }
+
+        value.into()
+    }
+}
+
+/// MD5 of the parts joined by `:`, as lowercase hex
+#[cfg(feature = "digest-auth")]
+fn md5_hex(parts: &[&str]) -> [u8; 32] {
+    let mut ctx = md5::Context::new();
+    for (idx, part) in parts.iter().enumerate() {
+        if idx > 0 {
+            ctx.consume(b":");
+        }
+        ctx.consume(part.as_bytes());
+    }
+
+    let mut hex = [0; 32];
+    for (idx, b) in ctx.compute().0.iter().enumerate() {
+        hex[idx * 2] = HEX[usize::from(b >> 4)];
+        hex[idx * 2 + 1] = HEX[usize::from(b & 0xf)];
+    }
+    hex
+}
+
+/// The nonce count as 8 hex digits
+#[cfg(feature = "digest-auth")]
+fn nc_hex(nc: u32) -> [u8; 8] {
+    let mut hex = [0; 8];
+    for (idx, digit) in hex.iter_mut().enumerate() {
+        *digit = HEX[((nc >> (28 - idx * 4)) & 0xf) as usize];
+    }
+    hex
+}
+
+#[cfg(feature = "digest-auth")]
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+#[cfg(feature = "digest-auth")]
+fn push_param(value: &mut alloc::string::String, name: &str, param: &str, quoted: bool) {
+    // the first parameter follows the scheme, the others are comma separated
+    value.push_str(if value.contains('=') { ", " } else { " " });
+    value.push_str(name);
+    value.push('=');
+    if !quoted {
+        value.push_str(param);
+        return;
+    }
+
+    value.push('"');
+    for c in param.chars() {
+        if matches!(c, '"' | '\\') {
+            value.push('\\');
+        }
+        value.push(c);
+    }
+    value.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "digest-auth")]
+    #[test]
+    fn digest_rfc2617() {
+        let challenge = AuthChallenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+        let digest = DigestAuth::new(&challenge, "Mufasa", "Circle Of Life").unwrap();
+        let method = crate::request::Method::Get;
+
+        assert_eq!(
+            &digest.response(method, "/dir/index.html", "0a4f113b", 1),
+            b"6629fae49393a05397450978507c4ef1"
+        );
+        assert_eq!(
+            digest
+                .authorization(method, "/dir/index.html", "0a4f113b", 1)
+                .as_ref(),
+            br#"Digest username="Mufasa", realm="testrealm@host.com", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", uri="/dir/index.html", qop=auth, nc=00000001, cnonce="0a4f113b", response="6629fae49393a05397450978507c4ef1", opaque="5ccc069c403ebaf9f0171e9517f40e41""#
+        );
+
+        let basic = AuthChallenge::parse("Basic realm=\"x\"").unwrap();
+        assert_eq!(DigestAuth::new(&basic, "a", "b"), None);
+        let auth_int =
+            AuthChallenge::parse("Digest realm=\"x\", nonce=\"n\", qop=\"auth-int\"").unwrap();
+        assert_eq!(DigestAuth::new(&auth_int, "a", "b"), None);
+    }
+
+    #[test]
+    fn parse_challenge() {
+        let challenge = AuthChallenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque=5ccc069c403ebaf9f0171e9517f40e41"#,
+        )
+        .unwrap();
+        assert_eq!(challenge.scheme, "Digest");
+        assert_eq!(challenge.realm(), Some("testrealm@host.com"));
+        assert_eq!(challenge.param("QOP"), Some("auth,auth-int"));
+        assert_eq!(
+            challenge.param("nonce"),
+            Some("dcd98b7102dd2f0e8b11d0f600bfb0c093")
+        );
+        assert_eq!(
+            challenge.param("opaque"),
+            Some("5ccc069c403ebaf9f0171e9517f40e41")
+        );
+        assert_eq!(challenge.params().count(), 4);
+
+        let challenge = AuthChallenge::parse("Basic realm=\"a \\\"b\\\"\"").unwrap();
+        assert_eq!(challenge.scheme, "Basic");
+        assert_eq!(challenge.realm(), Some("a \\\"b\\\""));
+
+        let challenge = AuthChallenge::parse("Bearer").unwrap();
+        assert_eq!(challenge.scheme, "Bearer");
+        assert_eq!(challenge.rest, "");
+        assert_eq!(challenge.realm(), None);
+
+        assert_eq!(AuthChallenge::parse(""), None);
+        assert_eq!(AuthChallenge::parse("=realm"), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! Incremental decoding of bodies sent with `Transfer-Encoding: chunked`.
+
+use core::ops::Range;
+
+use embedded_io::Write;
+
+use crate::{Error, Result};
+
+/// Progress of a [`ChunkedDecoder`] after decoding some input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeStatus {
+    /// All of the input was decoded, the body continues in the next input
+    NeedMore,
+    /// The terminating chunk and the trailers ended after `consumed` bytes of the input.
+    /// Anything after that, like the next response on the connection, isn't part of the body.
+    Done { consumed: usize },
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeStatus {
+    fn format(&self, fmt: defmt::Formatter) {
+        match self {
+            DecodeStatus::NeedMore => defmt::write!(fmt, "NeedMore"),
+            DecodeStatus::Done { consumed } => {
+                defmt::write!(fmt, "Done {{ consumed: {=usize} }}", consumed)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Parsing the hex size of the next chunk, `digits` tells if any were seen yet
+    Size {
+        size: usize,
+        digits: bool,
+    },
+    /// Skipping chunk extensions, or whitespace after the size, until the end of the line
+    Extension {
+        size: usize,
+    },
+    /// Expecting the LF ending the size line
+    SizeLf {
+        size: usize,
+    },
+    /// Passing on the data of a chunk
+    Data {
+        remaining: usize,
+    },
+    /// Expecting the CR after the data of a chunk
+    DataCr,
+    /// Expecting the LF after the data of a chunk
+    DataLf,
+    /// At the start of a trailer line, or of the empty line ending the body
+    TrailerStart,
+    /// Skipping a trailer until the end of the line
+    Trailer,
+    /// Expecting the LF ending a trailer
+    TrailerLf,
+    /// Expecting the LF of the empty line ending the body
+    FinalLf,
+    Done,
+}
+
+/// Decodes a chunked body fed to it in pieces of any size, e.g. as read from a socket,
+/// without buffering it. Chunk extensions and trailers are skipped.
+///
+/// The decoded data is never longer than the input it came from, so input no larger than
+/// the space left in the output always fits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedDecoder {
+    state: State,
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkedDecoder {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Size {
+                size: 0,
+                digits: false,
+            },
+        }
+    }
+
+    /// Returns true once the terminating chunk and the trailers have been decoded
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Returns true once the terminating chunk has been decoded, so only trailers may follow
+    pub(crate) fn chunks_done(&self) -> bool {
+        matches!(
+            self.state,
+            State::TrailerStart | State::Trailer | State::TrailerLf | State::FinalLf | State::Done
+        )
+    }
+
+    /// Decodes `input`, which continues the input of the previous calls, writing the data
+    /// of the chunks to `out`. Fails with [`Error::InvalidChunk`] if the framing is invalid.
+    pub fn decode<W: Write>(&mut self, input: &[u8], out: &mut W) -> Result<DecodeStatus>
+    where
+        Error: From<W::Error>,
+    {
+        let mut pos = 0;
+
+        while pos < input.len() && self.state != State::Done {
+            let (consumed, data) = self.next_data(&input[pos..])?;
+            if let Some(data) = data {
+                out.write_all(&input[pos..][data])?;
+            }
+            pos += consumed;
+        }
+
+        Ok(match self.state {
+            State::Done => DecodeStatus::Done { consumed: pos },
+            _ => DecodeStatus::NeedMore,
+        })
+    }
+
+    /// Decodes `input` up to the end of the next piece of chunk data, or up to the trailers
+    /// following the terminating chunk, for callers using the data in place.
+    /// Returns the number of bytes consumed and where in `input` the data is.
+    pub(crate) fn next_data(&mut self, input: &[u8]) -> Result<(usize, Option<Range<usize>>)> {
+        let mut pos = 0;
+
+        while pos < input.len() && self.state != State::Done {
+            if let State::Data { remaining } = self.state {
+                let num = remaining.min(input.len() - pos);
+                self.state = match remaining - num {
+                    0 => State::DataCr,
+                    remaining => State::Data { remaining },
+                };
+                return Ok((pos + num, Some(pos..pos + num)));
+            }
+
+            let terminating = matches!(self.state, State::SizeLf { size: 0 });
+            self.state = self.next_state(input[pos])?;
+            pos += 1;
+            if terminating {
+                break;
+            }
+        }
+
+        Ok((pos, None))
+    }
+
+    /// The state after the framing byte `b`
+    fn next_state(&self, b: u8) -> Result<State> {
+        Ok(match (self.state, b) {
+            // no chunk can be larger than a slice holding it could be
+            (State::Size { size, .. }, b) if b.is_ascii_hexdigit() => State::Size {
+                size: size
+                    .checked_mul(16)
+                    .and_then(|size| size.checked_add((b as char).to_digit(16)? as usize))
+                    .filter(|&size| size <= isize::MAX as usize)
+                    .ok_or(Error::InvalidChunk)?,
+                digits: true,
+            },
+            (State::Size { size, digits: true }, b';' | b' ' | b'\t') => State::Extension { size },
+            (State::Size { size, digits: true }, b'\r') => State::SizeLf { size },
+            (State::Extension { size }, b'\r') => State::SizeLf { size },
+            (State::Extension { size }, _) => State::Extension { size },
+            (State::SizeLf { size: 0 }, b'\n') => State::TrailerStart,
+            (State::SizeLf { size }, b'\n') => State::Data { remaining: size },
+            (State::DataCr, b'\r') => State::DataLf,
+            (State::DataLf, b'\n') => State::Size {
+                size: 0,
+                digits: false,
+            },
+            (State::TrailerStart, b'\r') => State::FinalLf,
+            (State::Trailer, b'\r') => State::TrailerLf,
+            (State::TrailerStart | State::Trailer, _) => State::Trailer,
+            (State::TrailerLf, b'\n') => State::TrailerStart,
+            (State::FinalLf, b'\n') => State::Done,
+            _ => return Err(Error::InvalidChunk),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const BODY: &[u8] =
+        b"7\r\nchunked\r\nC;ext=1\r\n body, split\r\n0\r\nx-checksum: abcd\r\n\r\nHTTP/1.1";
+
+    #[test]
+    fn byte_by_byte() {
+        let mut decoder = ChunkedDecoder::new();
+        let mut out = Vec::new();
+        let mut end = None;
+
+        for (idx, b) in BODY.iter().enumerate() {
+            match decoder.decode(core::slice::from_ref(b), &mut out).unwrap() {
+                DecodeStatus::NeedMore => {}
+                DecodeStatus::Done { consumed } => {
+                    assert_eq!(consumed, 1);
+                    end = Some(idx + 1);
+                    break;
+                }
+            }
+        }
+        assert_eq!(out, b"chunked body, split");
+        assert_eq!(&BODY[end.unwrap()..], b"HTTP/1.1");
+        assert!(decoder.is_done());
+    }
+
+    #[test]
+    fn split_reads() {
+        for split in 0..BODY.len() - 8 {
+            let mut decoder = ChunkedDecoder::new();
+            let mut out = Vec::new();
+            assert_eq!(
+                decoder.decode(&BODY[..split], &mut out).unwrap(),
+                DecodeStatus::NeedMore
+            );
+            assert_eq!(
+                decoder.decode(&BODY[split..], &mut out).unwrap(),
+                DecodeStatus::Done {
+                    consumed: BODY.len() - 8 - split
+                },
+                "{}",
+                split
+            );
+            assert_eq!(out, b"chunked body, split");
+        }
+    }
+
+    #[test]
+    fn invalid_framing() {
+        for body in [
+            &b"x\r\n"[..],
+            b"\r\n",
+            b"3\r\nabcd\r\n",
+            b"3\nabc\r\n",
+            b"fffffffffffffffffffffff\r\n",
+            b"0\r\n\r\r",
+        ] {
+            let res = ChunkedDecoder::new().decode(body, &mut Vec::new());
+            assert!(matches!(res, Err(Error::InvalidChunk)), "{:?}", body);
+        }
+    }
+}
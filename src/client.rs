@@ -0,0 +1,508 @@
+//! Blocking client sending requests over an `embedded_io` connection.
+
+use alloc::vec::Vec;
+use embedded_io::{Read, Write};
+
+use crate::header::HeaderStorage;
+use crate::request::{Header, Request, ToRequestBody, Version};
+use crate::response::{read_progress, HeaderScan, OwnedResponse, Response};
+use crate::Result;
+
+/// Default limit of the size of a response, see [`Client::with_max_response_bytes`]
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// Default limit of reads per response, see [`Client::with_max_reads`]
+pub const DEFAULT_MAX_READS: usize = 256;
+
+/// Sends requests over a connection, such as a TCP or TLS socket,
+/// and reads back the whole response.
+///
+/// The connection is kept open between requests until a response closes it,
+/// see [`Client::is_reusable`] and [`Client::reconnect`].
+pub struct Client<T> {
+    io: T,
+    max_response_bytes: usize,
+    max_reads: usize,
+    /// bytes read past the end of the last response
+    leftover: Vec<u8>,
+    reusable: bool,
+}
+
+impl<T: Read + Write> Client<T>
+where
+    crate::Error: From<T::Error>,
+{
+    pub fn new(io: T) -> Self {
+        Self {
+            io,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_reads: DEFAULT_MAX_READS,
+            leftover: Vec::new(),
+            reusable: true,
+        }
+    }
+
+    /// Limits the size of a response, larger responses fail with `Error::ResponseTooLarge`
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Limits the number of reads per response, so a server trickling bytes can't keep
+    /// the client busy forever. Exceeding it fails with `Error::TooManyReads`.
+    pub fn with_max_reads(mut self, max_reads: usize) -> Self {
+        self.max_reads = max_reads;
+        self
+    }
+
+    /// Returns the connection. Bytes already read past the last response are dropped,
+    /// see [`Client::into_parts`] to keep them.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Returns the connection together with the bytes already read past the last response.
+    /// After `101 Switching Protocols` these are the start of the upgraded protocol,
+    /// e.g. the first WebSocket frames, which must be handled before reading from the connection.
+    pub fn into_parts(self) -> (T, Vec<u8>) {
+        (self.io, self.leftover)
+    }
+
+    /// Returns true if another request can be sent on the connection. This is not the case
+    /// after `Connection: close`, an HTTP/1.0 request, a protocol switch, the connection closing, or a failed `send`.
+    pub fn is_reusable(&self) -> bool {
+        self.reusable
+    }
+
+    /// Replaces the connection, e.g. after it was closed, and returns the old one
+    pub fn reconnect(&mut self, io: T) -> T {
+        self.leftover.clear();
+        self.reusable = true;
+        core::mem::replace(&mut self.io, io)
+    }
+
+    /// Writes the request and reads the response to it.
+    /// Interim `1xx` responses, like `103 Early Hints`, are skipped.
+    ///
+    /// A request with `Expect: 100-continue` is sent without its body, which only follows once
+    /// the server answers `100 Continue`. If the final response comes instead, the body is never
+    /// sent and the connection isn't reused. Servers ignoring `Expect` keep waiting for the body,
+    /// so only use it with servers known to support it.
+    ///
+    /// Fails with `Error::ConnectionClosed` if the connection isn't reusable.
+    pub fn send<'a, B: ToRequestBody, S: HeaderStorage<'a>>(
+        &mut self,
+        req: &Request<'a, B, S>,
+    ) -> Result<OwnedResponse> {
+        self.send_with_interim(req, |_| {})
+    }
+
+    /// Like [`Client::send`], passing each interim response, like `103 Early Hints`,
+    /// to `on_interim` before it is skipped
+    pub fn send_with_interim<'a, B: ToRequestBody, S: HeaderStorage<'a>>(
+        &mut self,
+        req: &Request<'a, B, S>,
+        mut on_interim: impl FnMut(Response<'_>),
+    ) -> Result<OwnedResponse> {
+        if !self.reusable {
+            return Err(crate::Error::ConnectionClosed);
+        }
+        // the connection is in an unknown state if anything below fails
+        self.reusable = false;
+
+        let mut body_pending = expects_continue(&req.header);
+        match body_pending {
+            true => req.write_header_only(&mut self.io)?,
+            false => req.write_to(&mut self.io)?,
+        }
+        self.io.flush()?;
+
+        let mut buf = core::mem::take(&mut self.leftover);
+        let mut eof = false;
+        let mut reads = 0;
+        let mut scan = HeaderScan::new();
+
+        loop {
+            // the response to a HEAD request has no body, whatever its headers say
+            if let Some(len) = read_progress(&buf, eof, &mut scan, Some(req.header.method))? {
+                let mut resp = Response::new(&buf[..len]);
+                let status_code = resp.status_code()?;
+                if resp.is_interim()? {
+                    let is_continue = resp.is_continue()?;
+                    on_interim(resp);
+                    buf.drain(..len);
+                    scan = HeaderScan::new();
+
+                    if is_continue && body_pending {
+                        req.write_body_to(&mut self.io)?;
+                        self.io.flush()?;
+                        body_pending = false;
+                    }
+                    continue;
+                }
+
+                // without keep-alive negotiated, HTTP/1.0 servers close after the response,
+                // and the server can't tell where the next request starts if the body was skipped
+                self.reusable = !eof
+                    && !body_pending
+                    && status_code != 101
+                    && req.header.version != Version::Http10
+                    && !resp.is_connection_close()?;
+                // keep what belongs to the next response
+                self.leftover = buf.split_off(len);
+                return Ok(OwnedResponse::new(buf));
+            }
+
+            if reads == self.max_reads {
+                return Err(crate::Error::TooManyReads);
+            }
+            reads += 1;
+
+            let mut chunk = [0; 512];
+            let num = self.io.read(&mut chunk)?;
+            eof = num == 0;
+            buf.extend_from_slice(&chunk[..num]);
+
+            if buf.len() > self.max_response_bytes {
+                return Err(crate::Error::ResponseTooLarge);
+            }
+        }
+    }
+}
+
+/// Returns true if the request asks the server to confirm before the body is sent
+fn expects_continue<'a, S: HeaderStorage<'a>>(header: &Header<'a, S>) -> bool {
+    header.headers.as_slice().iter().any(|(key, value)| {
+        key.inner.eq_ignore_ascii_case("expect")
+            && value.as_ref().eq_ignore_ascii_case(b"100-continue")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::{Method, RequestBuilder};
+    use embedded_io::ErrorType;
+
+    /// Connection answering with canned bytes, recording what was written
+    struct MockIo<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl ErrorType for MockIo<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for MockIo<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockIo<'_> {
+        fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn send() {
+        let req = RequestBuilder::post("http://test.com/")
+            .unwrap()
+            .expect_continue()
+            .body("hei")
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input:
+                b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello",
+            output: Vec::new(),
+        });
+
+        let resp = client.send(&req).unwrap();
+        let mut parsed = resp.response();
+        assert_eq!(parsed.status_code().unwrap(), 200);
+        assert_eq!(parsed.body().unwrap(), b"hello");
+
+        assert_eq!(client.into_inner().output, req.to_vec().unwrap());
+    }
+
+    /// Connection answering each read with the next response, recording how much was written
+    /// before each read
+    struct Exchange<'a> {
+        responses: &'a [&'a [u8]],
+        output: Vec<u8>,
+        written_before_read: Vec<usize>,
+    }
+
+    impl ErrorType for Exchange<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Exchange<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            self.written_before_read.push(self.output.len());
+            match self.responses.split_first() {
+                Some((&response, rest)) => {
+                    self.responses = rest;
+                    let mut response = response;
+                    response.read(buf)
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    impl Write for Exchange<'_> {
+        fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn expect_continue() {
+        let req = RequestBuilder::post("http://test.com/")
+            .unwrap()
+            .expect_continue()
+            .body("hei")
+            .unwrap();
+        let mut header = Vec::new();
+        req.write_header_only(&mut header).unwrap();
+
+        let mut client = Client::new(Exchange {
+            responses: &[
+                b"HTTP/1.1 100 Continue\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n",
+            ],
+            output: Vec::new(),
+            written_before_read: Vec::new(),
+        });
+        let resp = client.send(&req).unwrap();
+        assert_eq!(resp.response().status_code().unwrap(), 200);
+        assert!(client.is_reusable());
+
+        // the body is only written once the 100 was read
+        let io = client.into_inner();
+        assert_eq!(io.written_before_read, [header.len(), header.len() + 3]);
+        assert_eq!(io.output, req.to_vec().unwrap());
+
+        // rejected without a 100, so the body is never sent
+        let mut client = Client::new(Exchange {
+            responses: &[b"HTTP/1.1 413 Content Too Large\r\ncontent-length: 0\r\n\r\n"],
+            output: Vec::new(),
+            written_before_read: Vec::new(),
+        });
+        let resp = client.send(&req).unwrap();
+        assert_eq!(resp.response().status_code().unwrap(), 413);
+        assert!(!client.is_reusable());
+        assert_eq!(client.into_inner().output, header);
+    }
+
+    #[test]
+    fn keep_alive() {
+        let get = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\naHTTP/1.1 200 OK\r\ncontent-length: 1\r\nconnection: close\r\n\r\nb",
+            output: Vec::new(),
+        });
+
+        let first = client.send(&get).unwrap();
+        assert_eq!(first.response().body().unwrap(), b"a");
+        assert!(client.is_reusable());
+
+        let second = client.send(&get).unwrap();
+        assert_eq!(second.response().body().unwrap(), b"b");
+        assert!(!client.is_reusable());
+
+        assert!(matches!(
+            client.send(&get),
+            Err(crate::Error::ConnectionClosed)
+        ));
+
+        let old = client.reconnect(MockIo {
+            input: b"HTTP/1.1 204 No Content\r\n\r\n",
+            output: Vec::new(),
+        });
+        assert_eq!(
+            old.output,
+            [get.to_vec().unwrap(), get.to_vec().unwrap()].concat()
+        );
+        assert_eq!(
+            client.send(&get).unwrap().response().status_code().unwrap(),
+            204
+        );
+        assert!(client.is_reusable());
+    }
+
+    #[test]
+    fn switching_protocols() {
+        let get = RequestBuilder::get("http://test.com/chat")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\n\r\n\x81\x02hi",
+            output: Vec::new(),
+        });
+
+        let resp = client.send(&get).unwrap();
+        assert!(resp.response().is_switching_protocols().unwrap());
+        assert!(!client.is_reusable());
+
+        let (io, leftover) = client.into_parts();
+        assert_eq!(leftover, b"\x81\x02hi");
+        assert!(io.input.is_empty());
+    }
+
+    #[test]
+    fn http10_not_reusable() {
+        let get = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .version(Version::Http10)
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\na",
+            output: Vec::new(),
+        });
+
+        assert_eq!(client.send(&get).unwrap().response().body().unwrap(), b"a");
+        assert!(!client.is_reusable());
+    }
+
+    #[test]
+    fn early_hints() {
+        let get = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\na",
+            output: Vec::new(),
+        });
+
+        let mut links = Vec::new();
+        let resp = client
+            .send_with_interim(&get, |mut interim| {
+                links.push(interim.header_value("link").unwrap().into_owned())
+            })
+            .unwrap();
+        assert_eq!(resp.response().status_code().unwrap(), 200);
+        assert_eq!(links, ["</style.css>; rel=preload"]);
+    }
+
+    #[test]
+    fn head_request() {
+        let head = RequestBuilder::with_capacity(Method::Head, "http://test.com/", 0)
+            .unwrap()
+            .build()
+            .unwrap();
+        let get = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\na",
+            output: Vec::new(),
+        });
+
+        let resp = client.send(&head).unwrap();
+        assert_eq!(resp.response().entity_length().unwrap(), Some(100));
+        assert!(client.is_reusable());
+
+        let resp = client.send(&get).unwrap();
+        assert_eq!(resp.response().body().unwrap(), b"a");
+    }
+
+    #[test]
+    fn not_modified_pipelined() {
+        let get = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 304 Not Modified\r\n\r\nHTTP/1.1 304 Not Modified\r\ncontent-length: 5\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 1\r\n\r\na",
+            output: Vec::new(),
+        });
+
+        for _ in 0..2 {
+            let resp = client.send(&get).unwrap();
+            assert!(resp.response().is_not_modified().unwrap());
+            assert!(client.is_reusable());
+        }
+
+        let resp = client.send(&get).unwrap();
+        assert_eq!(resp.response().body().unwrap(), b"a");
+    }
+
+    #[test]
+    fn send_limits() {
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+        let input = b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello";
+
+        let io = MockIo {
+            input,
+            output: Vec::new(),
+        };
+        let mut client = Client::new(io).with_max_response_bytes(input.len() - 1);
+        assert!(matches!(
+            client.send(&req),
+            Err(crate::Error::ResponseTooLarge)
+        ));
+
+        let io = MockIo {
+            input,
+            output: Vec::new(),
+        };
+        let mut client = Client::new(io).with_max_response_bytes(input.len());
+        assert!(client.send(&req).is_ok());
+
+        let io = MockIo {
+            input,
+            output: Vec::new(),
+        };
+        let mut client = Client::new(io).with_max_reads(0);
+        assert!(matches!(client.send(&req), Err(crate::Error::TooManyReads)));
+    }
+
+    #[test]
+    fn send_incomplete() {
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut client = Client::new(MockIo {
+            input: b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhel",
+            output: Vec::new(),
+        });
+
+        assert!(matches!(
+            client.send(&req),
+            Err(crate::Error::Response(
+                crate::response::ResponseError::Incomplete
+            ))
+        ));
+    }
+}
@@ -4,6 +4,10 @@ use embedded_io::WriteFmtError;
 #[allow(unused_imports)]
 use crate::prelude::*;
 
+use crate::response::ResponseError;
+use crate::uri::UriError;
+use core::str::Utf8Error;
+
 #[derive(Debug)]
 pub enum Error {
     FmtError,
@@ -12,9 +16,63 @@ pub enum Error {
     DefmtFmtError,
     #[cfg(feature = "serde_json")]
     SerdeError(serde_json::Error),
+    #[cfg(feature = "serde_json_core")]
+    SerdeCoreError(serde_json_core::de::Error),
+    /// Serializing a JSON body with `serde-json-core` failed for another reason than a full buffer
+    #[cfg(feature = "serde_json_core")]
+    SerdeCoreSerError(serde_json_core::ser::Error),
+    /// Serializing a CBOR body failed, the io error is dropped
+    #[cfg(feature = "cbor")]
+    CborSerError(ciborium::ser::Error<()>),
+    /// Deserializing a CBOR body failed, the io error is dropped
+    #[cfg(feature = "cbor")]
+    CborDeError(ciborium::de::Error<()>),
     ErrorKind(ErrorKind),
     Infallible(core::convert::Infallible),
-    InvalidUri,
+    InvalidUri(UriError),
+    /// A chunk size line isn't valid hex, or a chunk isn't terminated by CRLF
+    InvalidChunk,
+    Utf8Error(Utf8Error),
+    /// Parsing the response failed
+    Response(ResponseError),
+    /// The buffer is too small, holds the size of the buffer and the size needed
+    BufferTooSmall(usize, usize),
+    /// The header storage is full, holds the number of headers it can store
+    TooManyHeaders(usize),
+    /// A body wrote a different number of bytes than its declared content length
+    #[cfg(feature = "strict")]
+    ContentLengthMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    /// The response exceeds the size limit of the client
+    ResponseTooLarge,
+    /// The response wasn't complete within the read limit of the client
+    TooManyReads,
+    /// The connection can't be reused for another request
+    ConnectionClosed,
+    /// A header name isn't a valid token
+    InvalidHeaderKey,
+    /// A header value contains CR, LF or NUL
+    InvalidHeaderValue,
+    /// The method isn't one of [`crate::request::Method`]
+    UnknownMethod,
+    /// The HTTP version isn't HTTP/1.0 or HTTP/1.1
+    UnsupportedVersion,
+    /// The body doesn't declare its content length, so the size of the request isn't known
+    UnknownContentLength,
+    /// The request line isn't `<method> <target> HTTP/<major>.<minor>`
+    InvalidRequestLine,
+    /// The buffer ends before the empty line closing the header, read more and parse again
+    Incomplete,
+    /// The message has both `Transfer-Encoding` and `Content-Length`, which peers could
+    /// disagree on, letting one message smuggle in another
+    AmbiguousFraming,
+    /// The input isn't valid base64, see [`crate::util::base64::decode_into`]
+    InvalidBase64,
+    /// Converting to a type of the `http` crate failed
+    #[cfg(feature = "http")]
+    Http(http::Error),
 }
 
 #[cfg(feature = "defmt")]
@@ -27,16 +85,50 @@ impl defmt::Format for Error {
             Error::WriteZero => {
                 defmt::write!(fmt, "WriteZero");
             }
-            #[cfg(feature = "serde_json")]
-            Error::SerdeError(e) => {
-                #[cfg(not(feature = "alloc"))]
+            #[cfg(all(feature = "serde_json", not(feature = "alloc")))]
+            Error::SerdeError(_) => {
                 defmt::write!(fmt, "SerdeError()");
-
-                #[cfg(feature = "alloc")]
-                {
-                    use alloc::string::ToString;
-                    defmt::write!(fmt, "SerdeError({})", e.to_string());
-                }
+            }
+            #[cfg(all(feature = "serde_json", feature = "alloc"))]
+            Error::SerdeError(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "SerdeError({})", e.to_string());
+            }
+            #[cfg(all(feature = "serde_json_core", not(feature = "alloc")))]
+            Error::SerdeCoreError(_) => {
+                defmt::write!(fmt, "SerdeCoreError()");
+            }
+            #[cfg(all(feature = "serde_json_core", feature = "alloc"))]
+            Error::SerdeCoreError(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "SerdeCoreError({})", e.to_string());
+            }
+            #[cfg(all(feature = "serde_json_core", not(feature = "alloc")))]
+            Error::SerdeCoreSerError(_) => {
+                defmt::write!(fmt, "SerdeCoreSerError()");
+            }
+            #[cfg(all(feature = "serde_json_core", feature = "alloc"))]
+            Error::SerdeCoreSerError(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "SerdeCoreSerError({})", e.to_string());
+            }
+            #[cfg(all(feature = "cbor", not(feature = "alloc")))]
+            Error::CborSerError(_) => {
+                defmt::write!(fmt, "CborSerError()");
+            }
+            #[cfg(all(feature = "cbor", feature = "alloc"))]
+            Error::CborSerError(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "CborSerError({})", e.to_string());
+            }
+            #[cfg(all(feature = "cbor", not(feature = "alloc")))]
+            Error::CborDeError(_) => {
+                defmt::write!(fmt, "CborDeError()");
+            }
+            #[cfg(all(feature = "cbor", feature = "alloc"))]
+            Error::CborDeError(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "CborDeError({})", e.to_string());
             }
             Error::FmtError => {
                 defmt::write!(fmt, "FmtError");
@@ -47,8 +139,83 @@ impl defmt::Format for Error {
             Error::Infallible(e) => {
                 defmt::write!(fmt, "Infallible({:?})", e);
             }
-            Error::InvalidUri => {
-                defmt::write!(fmt, "InvalidUri");
+            Error::InvalidUri(e) => {
+                defmt::write!(fmt, "InvalidUri({})", e);
+            }
+            Error::InvalidChunk => {
+                defmt::write!(fmt, "InvalidChunk");
+            }
+            #[cfg(not(feature = "alloc"))]
+            Error::Utf8Error(_) => {
+                defmt::write!(fmt, "Utf8Error()");
+            }
+            #[cfg(feature = "alloc")]
+            Error::Utf8Error(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "Utf8Error({})", e.to_string());
+            }
+            Error::Response(e) => {
+                defmt::write!(fmt, "Response({})", e);
+            }
+            Error::BufferTooSmall(have, need) => {
+                defmt::write!(fmt, "BufferTooSmall({}, {})", have, need);
+            }
+            Error::TooManyHeaders(capacity) => {
+                defmt::write!(fmt, "TooManyHeaders({})", capacity);
+            }
+            Error::ResponseTooLarge => {
+                defmt::write!(fmt, "ResponseTooLarge");
+            }
+            Error::TooManyReads => {
+                defmt::write!(fmt, "TooManyReads");
+            }
+            Error::ConnectionClosed => {
+                defmt::write!(fmt, "ConnectionClosed");
+            }
+            Error::InvalidHeaderKey => {
+                defmt::write!(fmt, "InvalidHeaderKey");
+            }
+            Error::InvalidHeaderValue => {
+                defmt::write!(fmt, "InvalidHeaderValue");
+            }
+            Error::UnknownMethod => {
+                defmt::write!(fmt, "UnknownMethod");
+            }
+            Error::UnsupportedVersion => {
+                defmt::write!(fmt, "UnsupportedVersion");
+            }
+            Error::UnknownContentLength => {
+                defmt::write!(fmt, "UnknownContentLength");
+            }
+            Error::InvalidRequestLine => {
+                defmt::write!(fmt, "InvalidRequestLine");
+            }
+            Error::Incomplete => {
+                defmt::write!(fmt, "Incomplete");
+            }
+            Error::AmbiguousFraming => {
+                defmt::write!(fmt, "AmbiguousFraming");
+            }
+            Error::InvalidBase64 => {
+                defmt::write!(fmt, "InvalidBase64");
+            }
+            #[cfg(all(feature = "http", not(feature = "alloc")))]
+            Error::Http(_) => {
+                defmt::write!(fmt, "Http()");
+            }
+            #[cfg(all(feature = "http", feature = "alloc"))]
+            Error::Http(e) => {
+                use alloc::string::ToString;
+                defmt::write!(fmt, "Http({})", e.to_string());
+            }
+            #[cfg(feature = "strict")]
+            Error::ContentLengthMismatch { declared, actual } => {
+                defmt::write!(
+                    fmt,
+                    "ContentLengthMismatch {{ declared: {}, actual: {} }}",
+                    declared,
+                    actual
+                );
             }
         }
         // Format as hexadecimal.
@@ -76,6 +243,24 @@ impl From<core::convert::Infallible> for Error {
     }
 }
 
+impl From<UriError> for Error {
+    fn from(e: UriError) -> Self {
+        Self::InvalidUri(e)
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(e: Utf8Error) -> Self {
+        Self::Utf8Error(e)
+    }
+}
+
+impl From<ResponseError> for Error {
+    fn from(e: ResponseError) -> Self {
+        Self::Response(e)
+    }
+}
+
 impl From<ErrorKind> for Error {
     fn from(e: ErrorKind) -> Self {
         Self::ErrorKind(e)
@@ -89,6 +274,46 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+#[cfg(feature = "serde_json_core")]
+impl From<serde_json_core::de::Error> for Error {
+    fn from(e: serde_json_core::de::Error) -> Self {
+        Self::SerdeCoreError(e)
+    }
+}
+
+#[cfg(feature = "serde_json_core")]
+impl From<serde_json_core::ser::Error> for Error {
+    fn from(e: serde_json_core::ser::Error) -> Self {
+        Self::SerdeCoreSerError(e)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<E> From<ciborium::ser::Error<E>> for Error {
+    fn from(e: ciborium::ser::Error<E>) -> Self {
+        use ciborium::ser::Error as SerError;
+
+        Self::CborSerError(match e {
+            SerError::Io(_) => SerError::Io(()),
+            SerError::Value(msg) => SerError::Value(msg),
+        })
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<E> From<ciborium::de::Error<E>> for Error {
+    fn from(e: ciborium::de::Error<E>) -> Self {
+        use ciborium::de::Error as DeError;
+
+        Self::CborDeError(match e {
+            DeError::Io(_) => DeError::Io(()),
+            DeError::Syntax(offset) => DeError::Syntax(offset),
+            DeError::Semantic(offset, msg) => DeError::Semantic(offset, msg),
+            DeError::RecursionLimitExceeded => DeError::RecursionLimitExceeded,
+        })
+    }
+}
+
 impl embedded_io::Error for Error {
     fn kind(&self) -> ErrorKind {
         match self {
@@ -102,5 +327,61 @@ impl embedded_io::Error for Error {
 mod unstable {
     use super::*;
 
-    impl core::error::Error for Error {}
+    impl core::error::Error for Error {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            match self {
+                Error::InvalidUri(e) => Some(e),
+                Error::Utf8Error(e) => Some(e),
+                Error::Response(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    impl core::error::Error for UriError {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Response;
+
+    fn build(uri: &str, key: &'static str) -> crate::Result<()> {
+        crate::request::RequestBuilder::get(uri)?
+            .insert_header((crate::header::HeaderKey::from_unchecked(key), "1".into()))
+            .build()?;
+        Ok(())
+    }
+
+    fn round_trip(resp: &[u8]) -> crate::Result<crate::status::StatusCode> {
+        let status_code = Response::new(resp).status_code()?;
+        core::str::from_utf8(resp)?;
+        Ok(status_code)
+    }
+
+    #[test]
+    fn request_errors_convert() {
+        assert!(build("http://test.com/", "x-ok").is_ok());
+        assert!(matches!(
+            build("test.com/", "x-ok"),
+            Err(Error::InvalidUri(UriError::MissingScheme))
+        ));
+        assert!(matches!(
+            build("http://test.com/", "x bad"),
+            Err(Error::InvalidHeaderKey)
+        ));
+    }
+
+    #[test]
+    fn response_errors_convert() {
+        assert_eq!(round_trip(b"HTTP/1.1 200 OK\r\n\r\n").unwrap(), 200);
+        assert!(matches!(
+            round_trip(b"HTTP/1.1 200 OK\r\n"),
+            Err(Error::Response(ResponseError::Incomplete))
+        ));
+        assert!(matches!(
+            round_trip(b"HTTP/1.1 200 \xff\r\n\r\n"),
+            Err(Error::Response(ResponseError::Utf8Error(_)))
+        ));
+    }
 }
@@ -46,6 +46,27 @@ impl<'a> HeaderKey<'a> {
             inner: Cow::Borrowed(self.inner.as_ref()),
         }
     }
+
+    /// Creates a key without checking that it is a valid token, for keys known to be valid
+    pub const fn from_unchecked(s: &'a str) -> Self {
+        HeaderKey {
+            inner: Cow::Borrowed(s),
+        }
+    }
+
+    /// Creates a key, failing with `Error::InvalidHeaderKey` if it isn't a valid token
+    pub fn checked(s: &'a str) -> crate::Result<Self> {
+        let key = HeaderKey::from(s);
+        if !key.is_valid() {
+            return Err(crate::Error::InvalidHeaderKey);
+        }
+        Ok(key)
+    }
+
+    /// Returns true if the key is a token, made of the `tchar` set of RFC 7230
+    pub fn is_valid(&self) -> bool {
+        !self.inner.is_empty() && self.inner.bytes().all(is_tchar)
+    }
 }
 
 impl<'a> HeaderValue<'a> {
@@ -66,6 +87,60 @@ impl<'a> HeaderValue<'a> {
             inner: Cow::Borrowed(self.inner.as_ref()),
         }
     }
+
+    /// The value as a string, if it is valid utf8
+    pub fn as_str(&self) -> Result<&str, core::str::Utf8Error> {
+        core::str::from_utf8(&self.inner)
+    }
+
+    /// The value as a string with invalid utf8 replaced, for logging
+    pub fn to_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.inner)
+    }
+
+    /// Creates a value, failing with `Error::InvalidHeaderValue` if it contains CR, LF or NUL
+    pub fn checked(s: &'a [u8]) -> crate::Result<Self> {
+        let value = HeaderValue::from(s);
+        if !value.is_valid() {
+            return Err(crate::Error::InvalidHeaderValue);
+        }
+        Ok(value)
+    }
+
+    /// Returns true if the value contains no CR, LF or NUL,
+    /// which would allow injecting headers into the message
+    pub fn is_valid(&self) -> bool {
+        !self.inner.iter().any(|b| matches!(b, b'\r' | b'\n' | 0))
+    }
+
+    /// Compares the value to `other`, ignoring ASCII case
+    pub fn eq_ignore_ascii_case(&self, other: &[u8]) -> bool {
+        self.inner.eq_ignore_ascii_case(other)
+    }
+
+    /// Returns true if the value starts with `prefix`, ignoring ASCII case
+    pub fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.inner
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }
+
+    /// Returns true if `token` is one of the elements of a comma separated list like
+    /// `Connection: keep-alive, Upgrade`, ignoring ASCII case and surrounding whitespace
+    pub fn contains_token(&self, token: &[u8]) -> bool {
+        self.inner
+            .split(|b| *b == b',')
+            .any(|element| element.trim_ascii().eq_ignore_ascii_case(token))
+    }
+}
+
+impl core::str::FromStr for HeaderKey<'static> {
+    type Err = crate::Error;
+
+    /// Parses a key, failing with `Error::InvalidHeaderKey` if it isn't a valid token
+    fn from_str(s: &str) -> crate::Result<Self> {
+        HeaderKey::checked(s).map(HeaderKey::into_owned)
+    }
 }
 
 impl<'a> From<&'a str> for HeaderKey<'a> {
@@ -108,11 +183,86 @@ impl<'a> From<&'a [u8]> for HeaderValue<'a> {
     }
 }
 
+/// Returns true for the characters allowed in a token, the `tchar` set of RFC 7230
+pub(crate) const fn is_tchar(b: u8) -> bool {
+    matches!(
+        b,
+        b'!' | b'#'
+            | b'$'
+            | b'%'
+            | b'&'
+            | b'\''
+            | b'*'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~'
+    ) || b.is_ascii_alphanumeric()
+}
+
+/// Header storage backed by a `Vec`, used by default
+pub type HeaderVec<'a> = alloc::vec::Vec<(HeaderKey<'a>, HeaderValue<'a>)>;
+
+/// Header storage with a fixed capacity of `N` headers, for building requests without an allocator
+#[cfg(feature = "heapless")]
+pub type HeaderMap<'a, const N: usize> = heapless::Vec<(HeaderKey<'a>, HeaderValue<'a>), N>;
+
+/// Storage for the headers of a request
+pub trait HeaderStorage<'a> {
+    /// Appends a header, failing with `Error::TooManyHeaders` if the storage is full
+    fn push(&mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> crate::Result<()>;
+
+    fn as_slice(&self) -> &[(HeaderKey<'a>, HeaderValue<'a>)];
+
+    /// Keeps only the headers for which `f` returns true
+    fn retain<F: FnMut(&(HeaderKey<'a>, HeaderValue<'a>)) -> bool>(&mut self, f: F);
+
+    /// Removes any headers with the same name, compared case-insensitively, then appends the header
+    fn insert(&mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> crate::Result<()> {
+        self.retain(|(key, _)| !key.inner.eq_ignore_ascii_case(&header.0.inner));
+        self.push(header)
+    }
+}
+
+impl<'a> HeaderStorage<'a> for HeaderVec<'a> {
+    fn push(&mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> crate::Result<()> {
+        alloc::vec::Vec::push(self, header);
+        Ok(())
+    }
+
+    fn as_slice(&self) -> &[(HeaderKey<'a>, HeaderValue<'a>)] {
+        self
+    }
+
+    fn retain<F: FnMut(&(HeaderKey<'a>, HeaderValue<'a>)) -> bool>(&mut self, f: F) {
+        alloc::vec::Vec::retain(self, f)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<'a, const N: usize> HeaderStorage<'a> for HeaderMap<'a, N> {
+    fn push(&mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> crate::Result<()> {
+        heapless::Vec::push(self, header).map_err(|_| crate::Error::TooManyHeaders(N))
+    }
+
+    fn as_slice(&self) -> &[(HeaderKey<'a>, HeaderValue<'a>)] {
+        self
+    }
+
+    fn retain<F: FnMut(&(HeaderKey<'a>, HeaderValue<'a>)) -> bool>(&mut self, f: F) {
+        heapless::Vec::retain(self, f)
+    }
+}
+
 macro_rules! impl_integer {
     ($int:ident) => {
         impl From<$int> for HeaderValue<'static> {
             fn from(s: $int) -> Self {
-                itoa::Buffer::new().format(s).to_owned().into()
+                crate::writer::IntBuffer::new().format(s).to_owned().into()
             }
         }
     };
@@ -131,6 +281,34 @@ impl_integer!(i128);
 impl_integer!(isize);
 impl_integer!(usize);
 
+/// Formats the date as an HTTP date (IMF-fixdate), like `Sun, 06 Nov 1994 08:49:37 GMT`
+impl From<chrono::DateTime<chrono::Utc>> for HeaderValue<'static> {
+    fn from(date: chrono::DateTime<chrono::Utc>) -> Self {
+        use chrono::{Datelike, Timelike};
+        use core::fmt::Write;
+
+        const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+
+        let mut value = String::with_capacity(29);
+        // writing to a string can't fail
+        let _ = write!(
+            value,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            DAYS[date.weekday().num_days_from_monday() as usize],
+            date.day(),
+            MONTHS[date.month0() as usize],
+            date.year(),
+            date.hour(),
+            date.minute(),
+            date.second()
+        );
+        value.into()
+    }
+}
+
 pub static ACCEPT: HeaderKey<'static> = HeaderKey::from_static("accept");
 
 pub static ACCEPT_CHARSET: HeaderKey<'static> = HeaderKey::from_static("accept-charset");
@@ -311,3 +489,56 @@ pub static X_DNS_PREFETCH_CONTROL: HeaderKey<'static> =
 pub static X_FRAME_OPTIONS: HeaderKey<'static> = HeaderKey::from_static("x-frame-options");
 
 pub static X_XSS_PROTECTION: HeaderKey<'static> = HeaderKey::from_static("x-xss-protection");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_as_str() {
+        let value = HeaderValue::from("text/plain");
+        assert_eq!(value.as_str(), Ok("text/plain"));
+        assert!(matches!(value.to_str_lossy(), Cow::Borrowed("text/plain")));
+
+        let value = HeaderValue::from(&b"caf\xe9"[..]);
+        assert!(value.as_str().is_err());
+        assert_eq!(value.to_str_lossy(), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn value_comparisons() {
+        let value = HeaderValue::from("Keep-Alive");
+        assert!(value.eq_ignore_ascii_case(b"keep-alive"));
+        assert!(!value.eq_ignore_ascii_case(b"keep"));
+        assert!(value.starts_with(b"KEEP"));
+        assert!(!value.starts_with(b"Keep-Alive, close"));
+
+        let value = HeaderValue::from(" keep-alive ,Upgrade,\tH2C ");
+        assert!(value.contains_token(b"upgrade"));
+        assert!(value.contains_token(b"h2c"));
+        assert!(value.contains_token(b"Keep-Alive"));
+        assert!(!value.contains_token(b"close"));
+        assert!(!value.contains_token(b"keep"));
+    }
+
+    #[test]
+    fn value_from_date() {
+        let date = chrono::DateTime::from_timestamp(784111777, 0).unwrap();
+        assert_eq!(
+            HeaderValue::from(date).as_str(),
+            Ok("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+
+        // the response parser reads back what the request side writes
+        for timestamp in [0, 784111777, 951782400, 1445412480, 4102444799] {
+            let date = chrono::DateTime::from_timestamp(timestamp, 0).unwrap();
+            let value = HeaderValue::from(date);
+            let buf = std::format!(
+                "HTTP/1.1 200 OK\r\nDate: {}\r\n\r\n",
+                value.as_str().unwrap()
+            );
+            let mut resp = crate::response::Response::new(buf.as_bytes());
+            assert_eq!(resp.date().unwrap(), date);
+        }
+    }
+}
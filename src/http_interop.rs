@@ -0,0 +1,281 @@
+//! Conversions from and to the types of the `http` crate,
+//! for sharing requests and parsed responses with code written against `http`.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::header::{HeaderKey, HeaderStorage, HeaderValue};
+use crate::request::{Header, Method, Request, Version, DEFAULT_USER_AGENT};
+use crate::response::{parse_status_line, Response, ResponseError};
+use crate::uri::Uri;
+use crate::{Error, Result};
+
+impl From<Method> for http::Method {
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Options => http::Method::OPTIONS,
+            Method::Get => http::Method::GET,
+            Method::Post => http::Method::POST,
+            Method::Put => http::Method::PUT,
+            Method::Delete => http::Method::DELETE,
+            Method::Head => http::Method::HEAD,
+            Method::Trace => http::Method::TRACE,
+            Method::Connect => http::Method::CONNECT,
+            Method::Patch => http::Method::PATCH,
+        }
+    }
+}
+
+/// Fails with `Error::UnknownMethod` for extension methods
+impl TryFrom<&http::Method> for Method {
+    type Error = Error;
+
+    fn try_from(method: &http::Method) -> Result<Self> {
+        method.as_str().parse()
+    }
+}
+
+impl From<Version> for http::Version {
+    fn from(version: Version) -> Self {
+        match version {
+            Version::Http10 => http::Version::HTTP_10,
+            Version::Http11 => http::Version::HTTP_11,
+        }
+    }
+}
+
+/// Fails with `Error::UnsupportedVersion` for versions other than HTTP/1.0 and HTTP/1.1
+impl TryFrom<http::Version> for Version {
+    type Error = Error;
+
+    fn try_from(version: http::Version) -> Result<Self> {
+        match version {
+            http::Version::HTTP_10 => Ok(Version::Http10),
+            http::Version::HTTP_11 => Ok(Version::Http11),
+            _ => Err(Error::UnsupportedVersion),
+        }
+    }
+}
+
+/// Only absolute uris, like `http://example.com/path`, can be converted
+impl TryFrom<&http::Uri> for Uri<'static> {
+    type Error = Error;
+
+    fn try_from(uri: &http::Uri) -> Result<Self> {
+        Uri::parse(uri.to_string())
+    }
+}
+
+impl TryFrom<&Uri<'_>> for http::Uri {
+    type Error = Error;
+
+    fn try_from(uri: &Uri<'_>) -> Result<Self> {
+        http::Uri::try_from(uri.as_str()).map_err(|e| Error::Http(e.into()))
+    }
+}
+
+/// Takes the uri from the request target, or from the `Host` header if the target is a path only.
+/// The `User-Agent` header replaces [`DEFAULT_USER_AGENT`].
+impl TryFrom<http::request::Parts> for Header<'static> {
+    type Error = Error;
+
+    fn try_from(parts: http::request::Parts) -> Result<Self> {
+        let uri = match (parts.uri.scheme(), parts.headers.get(http::header::HOST)) {
+            (None, Some(host)) => {
+                let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+                Uri::parse(alloc::format!(
+                    "http://{}{}",
+                    host.to_str().map_err(|_| Error::InvalidHeaderValue)?,
+                    path
+                ))?
+            }
+            _ => Uri::try_from(&parts.uri)?,
+        };
+
+        let headers = parts
+            .headers
+            .iter()
+            .filter(|(name, _)| *name != http::header::HOST && *name != http::header::USER_AGENT)
+            .map(|(name, value)| {
+                (
+                    HeaderKey::from(name.as_str()).into_owned(),
+                    HeaderValue::from(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Header {
+            method: Method::try_from(&parts.method)?,
+            uri,
+            headers,
+            no_content_length_on_empty: false,
+            asterisk_form: false,
+            version: parts.version.try_into()?,
+            user_agent: Some(match parts.headers.get(http::header::USER_AGENT) {
+                Some(ua) => HeaderValue::from(ua.as_bytes()).into_owned(),
+                None => DEFAULT_USER_AGENT,
+            }),
+        })
+    }
+}
+
+impl<'a, S: HeaderStorage<'a>> TryFrom<&Header<'a, S>> for http::request::Parts {
+    type Error = Error;
+
+    fn try_from(header: &Header<'a, S>) -> Result<Self> {
+        let mut builder = http::Request::builder()
+            .method(http::Method::from(header.method))
+            .uri(http::Uri::try_from(&header.uri)?)
+            .version(header.version.into());
+
+        if let Some(user_agent) = &header.user_agent {
+            builder = builder.header(http::header::USER_AGENT, user_agent.as_ref());
+        }
+
+        for (key, value) in header.headers.as_slice() {
+            builder = builder.header(key.inner.as_ref(), value.as_ref());
+        }
+
+        Ok(builder.body(()).map_err(Error::Http)?.into_parts().0)
+    }
+}
+
+impl<T> TryFrom<http::Request<T>> for Request<'static, T> {
+    type Error = Error;
+
+    fn try_from(req: http::Request<T>) -> Result<Self> {
+        let (parts, body) = req.into_parts();
+        Ok(Request {
+            header: parts.try_into()?,
+            body,
+        })
+    }
+}
+
+impl<'a, T, S: HeaderStorage<'a>> TryFrom<Request<'a, T, S>> for http::Request<T> {
+    type Error = Error;
+
+    fn try_from(req: Request<'a, T, S>) -> Result<Self> {
+        let parts = http::request::Parts::try_from(&req.header)?;
+        Ok(http::Request::from_parts(parts, req.body))
+    }
+}
+
+impl Response<'_> {
+    /// Converts the status line and headers to `http` types, leaving the body to the caller.
+    /// Headers continued using obsolete line folding are unfolded.
+    pub fn to_http_parts(&mut self) -> Result<http::response::Parts> {
+        let mut lines = self
+            .header_bytes()?
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty());
+
+        let status_line = lines.next().ok_or(ResponseError::InvalidStatusLine)?;
+        let (major, minor, status_code, _) = parse_status_line(status_line)?;
+        let version = match (major, minor) {
+            (1, 0) => http::Version::HTTP_10,
+            (1, 1) => http::Version::HTTP_11,
+            _ => return Err(Error::UnsupportedVersion),
+        };
+
+        let mut headers: Vec<(&[u8], Vec<u8>)> = Vec::new();
+        for line in lines {
+            match (line.first(), headers.last_mut()) {
+                (Some(b' ' | b'\t'), Some((_, value))) => {
+                    value.push(b' ');
+                    value.extend_from_slice(line.trim_ascii_start());
+                }
+                // lines without a colon are skipped, like in the header lookups of `Response`
+                _ => {
+                    if let Some(colon) = line.iter().position(|b| *b == b':') {
+                        headers.push((&line[..colon], line[colon + 1..].trim_ascii().to_vec()));
+                    }
+                }
+            }
+        }
+
+        let mut builder = http::Response::builder()
+            .status(status_code)
+            .version(version);
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+
+        Ok(builder.body(()).map_err(Error::Http)?.into_parts().0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestBuilder;
+
+    #[test]
+    fn request_round_trip() {
+        let req = RequestBuilder::post("http://test.com:8080/path?q=1")
+            .unwrap()
+            .insert_header(("x-token".into(), "abc".into()))
+            .version(Version::Http10)
+            .build_with_body("body")
+            .unwrap();
+
+        let http_req = http::Request::try_from(req).unwrap();
+        assert_eq!(http_req.method(), http::Method::POST);
+        assert_eq!(http_req.uri(), "http://test.com:8080/path?q=1");
+        assert_eq!(http_req.version(), http::Version::HTTP_10);
+        assert_eq!(http_req.headers()["x-token"], "abc");
+        assert_eq!(http_req.headers()["user-agent"], ":)");
+        assert_eq!(*http_req.body(), "body");
+
+        let req = Request::try_from(http_req).unwrap();
+        assert_eq!(req.header.method, Method::Post);
+        assert_eq!(req.header.uri.authority(), "test.com:8080");
+        assert_eq!(req.header.uri.path_and_query(), "/path?q=1");
+        assert_eq!(req.header.version, Version::Http10);
+        assert_eq!(req.header.user_agent, Some(DEFAULT_USER_AGENT));
+        assert_eq!(
+            req.header.headers,
+            [(HeaderKey::from("x-token"), HeaderValue::from("abc"))]
+        );
+    }
+
+    #[test]
+    fn response_parts() {
+        let mut resp = Response::new(
+            b"HTTP/1.0 404 Not Found\r\nContent-Type: text/plain\r\nX-Long: a\r\n b\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\nContent-Length: 2\r\n\r\nno",
+        );
+        let parts = resp.to_http_parts().unwrap();
+        assert_eq!(parts.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(parts.version, http::Version::HTTP_10);
+        assert_eq!(parts.headers[http::header::CONTENT_TYPE], "text/plain");
+        assert_eq!(parts.headers["x-long"], "a b");
+        assert_eq!(parts.headers.get_all("set-cookie").iter().count(), 2);
+        assert_eq!(resp.body().unwrap(), b"no");
+    }
+
+    #[test]
+    fn origin_form_uses_host() {
+        let http_req = http::Request::get("/health")
+            .header("Host", "test.com")
+            .body(())
+            .unwrap();
+        let req = Request::try_from(http_req).unwrap();
+        assert_eq!(req.header.uri.authority(), "test.com");
+        assert_eq!(req.header.uri.path_and_query(), "/health");
+        assert!(req.header.headers.is_empty());
+
+        let http_req = http::Request::get("/health").body(()).unwrap();
+        assert!(Request::try_from(http_req).is_err());
+
+        let http_req = http::Request::builder()
+            .method("BREW")
+            .uri("http://test.com/")
+            .body(())
+            .unwrap();
+        assert!(matches!(
+            Request::try_from(http_req),
+            Err(Error::UnknownMethod)
+        ));
+    }
+}
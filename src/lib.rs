@@ -8,9 +8,6 @@ extern crate std;
 extern crate alloc;
 
 mod prelude {
-    #[cfg(feature = "defmt")]
-    pub use defmt::{debug, error, info, warn};
-
     #[cfg(feature = "serde_json")]
     pub use serde::Serialize;
 }
@@ -18,14 +15,26 @@ mod prelude {
 pub mod error;
 pub mod request;
 pub mod response;
+pub mod status;
 
 pub mod mime;
 pub mod uri;
 
+pub mod auth;
+pub mod chunked;
 pub mod header;
+pub mod sse;
+pub mod util;
+pub mod writer;
 
-#[cfg(test)]
-pub(crate) mod test_client;
+#[cfg(feature = "alloc")]
+pub mod client;
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "http")]
+mod http_interop;
 
 pub use error::Error;
 
@@ -33,48 +42,14 @@ pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 pub use embedded_io::Write;
 
-/*
-#[cfg(test)]
-mod tests {
-    use core::str::from_utf8;
-
-    extern crate std;
-
-    use std::prelude::rust_2021::*;
-    use httptest::{Server, Expectation, matchers::*, responders::*};
-
-    use crate::test_client::TestClient;
-
-    #[test]
-    fn test_get() {
-        let server = Server::run();
-
-        server.expect(
-            Expectation::matching(request::method_path("GET", "/v1/health"))
-                .respond_with(status_code(200).body("Hello, world!").insert_header("Content-Type", "text/plain")),
-        );
-
-        let url = server.url("/v1/health");
-
-        let req = http::Request::get(url.clone())
-            .body(())
-            .unwrap();
-
-        let req = crate::request::RequestWrapper::new(req).to_vec().unwrap();
-
-
-        let mut client = TestClient::new(&server);
-
-        let resp = client.send(req.as_slice());
-
-        println!("{}", from_utf8(resp.as_slice()).unwrap());
-
-        let mut response = crate::response::Response::new(resp.as_slice());
-
-        assert_eq!(response.status_code().unwrap(), 200);
-        assert_eq!(response.body_as_str().unwrap(), "Hello, world!");
+/// Polls a future that never has to wait to completion
+#[cfg(all(test, feature = "async"))]
+pub(crate) fn block_on<F: core::future::Future>(fut: F) -> F::Output {
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    let mut fut = core::pin::pin!(fut);
+    loop {
+        if let core::task::Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
     }
-
 }
-
- */
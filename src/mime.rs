@@ -1,5 +1,218 @@
-use crate::header::{ HeaderValue};
+use crate::header::{is_tchar, HeaderValue};
 
-pub static APPLICATION_JSON: HeaderValue = HeaderValue::from_static(b"application/json");
-pub static APPLICATION_OCTET_STREAM: HeaderValue = HeaderValue::from_static(b"application/octet-stream");
-pub static TEXT_PLAIN_UTF_8: HeaderValue = HeaderValue::from_static(b"text/plain; charset=utf-8");
\ No newline at end of file
+/// A media type like `text/plain; charset=utf-8`, as used in `Content-Type` and `Accept`.
+/// Compares the type, subtype and parameters as written, without normalizing case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mime<'a> {
+    inner: &'a str,
+    /// position of the `/` between type and subtype
+    slash: usize,
+    /// end of `type/subtype`, where the parameters start
+    essence_end: usize,
+}
+
+impl<'a> Mime<'a> {
+    /// Creates a media type at compile time, panics if it doesn't start with `type/subtype`
+    pub const fn from_static(s: &'static str) -> Mime<'static> {
+        match split(s) {
+            Some((slash, essence_end)) => Mime {
+                inner: s,
+                slash,
+                essence_end,
+            },
+            None => panic!("media type must start with type/subtype"),
+        }
+    }
+
+    /// Parses a media type, returns `None` if it doesn't start with `type/subtype`
+    pub fn parse(s: &'a str) -> Option<Self> {
+        let s = s.trim_matches([' ', '\t']);
+        let (slash, essence_end) = split(s)?;
+        Some(Mime {
+            inner: s,
+            slash,
+            essence_end,
+        })
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.inner
+    }
+
+    /// The top level type, like `text` in `text/plain`
+    pub fn type_(&self) -> &'a str {
+        &self.inner[..self.slash]
+    }
+
+    /// The subtype, like `plain` in `text/plain`, including any `+suffix`
+    pub fn subtype(&self) -> &'a str {
+        &self.inner[self.slash + 1..self.essence_end]
+    }
+
+    /// `type/subtype` without the parameters
+    pub fn essence(&self) -> &'a str {
+        &self.inner[..self.essence_end]
+    }
+
+    /// Iterates over the parameters, with quotes around the values removed
+    pub fn params(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.inner[self.essence_end..]
+            .split(';')
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                let value = value.trim_matches([' ', '\t']);
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((name.trim_matches([' ', '\t']), value))
+            })
+    }
+
+    /// Value of the parameter with the given name, compared case-insensitively
+    pub fn param(&self, name: &str) -> Option<&'a str> {
+        self.params()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Finds the `/` and the end of `type/subtype`, checking that both are tokens
+const fn split(s: &str) -> Option<(usize, usize)> {
+    let bytes = s.as_bytes();
+    let mut slash = 0;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'/' if slash == 0 && idx > 0 => slash = idx,
+            b';' | b' ' | b'\t' => break,
+            b if is_tchar(b) => {}
+            _ => return None,
+        }
+        idx += 1;
+    }
+
+    if slash == 0 || slash + 1 == idx {
+        return None;
+    }
+
+    // only whitespace may come between the essence and the parameters
+    let mut rest = idx;
+    while rest < bytes.len() && (bytes[rest] == b' ' || bytes[rest] == b'\t') {
+        rest += 1;
+    }
+    if rest < bytes.len() && bytes[rest] != b';' {
+        return None;
+    }
+
+    Some((slash, idx))
+}
+
+impl core::fmt::Display for Mime<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.inner)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Mime<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.inner);
+    }
+}
+
+impl AsRef<[u8]> for Mime<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+}
+
+impl<'a> From<Mime<'a>> for HeaderValue<'a> {
+    fn from(mime: Mime<'a>) -> Self {
+        HeaderValue::from(mime.inner)
+    }
+}
+
+pub const APPLICATION_JSON: Mime = Mime::from_static("application/json");
+pub const APPLICATION_OCTET_STREAM: Mime = Mime::from_static("application/octet-stream");
+pub const TEXT_PLAIN_UTF_8: Mime = Mime::from_static("text/plain; charset=utf-8");
+pub const APPLICATION_FORM_URLENCODED: Mime =
+    Mime::from_static("application/x-www-form-urlencoded");
+pub const MULTIPART_FORM_DATA: Mime = Mime::from_static("multipart/form-data");
+pub const TEXT_HTML: Mime = Mime::from_static("text/html");
+pub const APPLICATION_XML: Mime = Mime::from_static("application/xml");
+pub const APPLICATION_CBOR: Mime = Mime::from_static("application/cbor");
+pub const TEXT_CSV: Mime = Mime::from_static("text/csv");
+pub const TEXT_EVENT_STREAM: Mime = Mime::from_static("text/event-stream");
+pub const IMAGE_PNG: Mime = Mime::from_static("image/png");
+
+/// The media type for a file extension like `json` or `png`, compared case-insensitively.
+/// Returns `None` for unknown extensions, fall back to [`APPLICATION_OCTET_STREAM`] for those.
+pub fn from_extension(ext: &str) -> Option<Mime<'static>> {
+    const EXTENSIONS: [(&str, Mime<'static>); 8] = [
+        ("json", APPLICATION_JSON),
+        ("png", IMAGE_PNG),
+        ("txt", TEXT_PLAIN_UTF_8),
+        ("bin", APPLICATION_OCTET_STREAM),
+        ("csv", TEXT_CSV),
+        ("xml", APPLICATION_XML),
+        ("html", TEXT_HTML),
+        ("htm", TEXT_HTML),
+    ];
+
+    EXTENSIONS
+        .iter()
+        .find(|(e, _)| e.eq_ignore_ascii_case(ext))
+        .map(|(_, mime)| *mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let mime = Mime::parse(" text/plain ; charset=\"utf-8\"; format=flowed").unwrap();
+        assert_eq!(mime.type_(), "text");
+        assert_eq!(mime.subtype(), "plain");
+        assert_eq!(mime.essence(), "text/plain");
+        assert_eq!(mime.param("Charset"), Some("utf-8"));
+        assert_eq!(mime.param("format"), Some("flowed"));
+        assert_eq!(mime.param("missing"), None);
+
+        assert_eq!(TEXT_PLAIN_UTF_8.essence(), "text/plain");
+        assert_eq!(TEXT_PLAIN_UTF_8.param("charset"), Some("utf-8"));
+        assert_eq!(
+            Mime::parse("application/problem+json").unwrap().subtype(),
+            "problem+json"
+        );
+
+        for invalid in [
+            "",
+            "text",
+            "text/",
+            "/plain",
+            "text/pl ain",
+            "te xt/plain",
+            "text/plain/x",
+        ] {
+            assert_eq!(Mime::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn extension() {
+        assert_eq!(from_extension("json"), Some(APPLICATION_JSON));
+        assert_eq!(from_extension("PNG"), Some(IMAGE_PNG));
+        assert_eq!(from_extension("txt"), Some(TEXT_PLAIN_UTF_8));
+        assert_eq!(from_extension("exe"), None);
+        assert_eq!(from_extension(""), None);
+    }
+
+    #[test]
+    fn into_header_value() {
+        let value: HeaderValue = APPLICATION_JSON.into();
+        assert_eq!(value, HeaderValue::from_static(b"application/json"));
+    }
+}
@@ -9,24 +9,70 @@ use crate::{Error, Result};
 #[allow(unused_imports)]
 use crate::prelude::*;
 
-use crate::header::{HeaderKey, HeaderValue};
+use crate::header::{HeaderKey, HeaderStorage, HeaderValue, HeaderVec};
 
 use core::fmt::Display;
 
-use crate::uri::Uri;
+use crate::mime::Mime;
+use crate::response::{Response, ResponseError};
+use crate::uri::{Uri, UriError};
 
-static USER_AGENT: HeaderValue<'static> = HeaderValue::from_static(b":)");
+/// The `User-Agent` sent unless [`RequestBuilder::user_agent`] sets another one.
+/// To send a product user agent with every request, set it in a helper creating the builders.
+pub const DEFAULT_USER_AGENT: HeaderValue<'static> = HeaderValue::from_static(b":)");
 
-pub struct Request<'a, T> {
-    pub header: Header<'a>,
+pub struct Request<'a, T, S = HeaderVec<'a>> {
+    pub header: Header<'a, S>,
     pub body: T,
 }
 
+/// The request line and headers of a request.
+/// The headers are kept in a `Vec` by default, see [`HeaderStorage`] for alternatives.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Header<'a> {
+pub struct Header<'a, S = HeaderVec<'a>> {
     pub method: Method,
     pub uri: Uri<'a>,
-    pub headers: Vec<(HeaderKey<'a>, HeaderValue<'a>)>,
+    pub headers: S,
+    /// Leave out `Content-Type` and `Content-Length` when the body is empty,
+    /// for servers and proxies that reject e.g. a GET with `Content-Length: 0`
+    pub no_content_length_on_empty: bool,
+    /// Target the whole server with `*` instead of the path of the uri, for `OPTIONS *`
+    pub asterisk_form: bool,
+    pub version: Version,
+    /// Sent as `User-Agent`, replacing any `User-Agent` in the headers.
+    /// `None` for a parsed request that had none, see [`parse`].
+    pub user_agent: Option<HeaderValue<'a>>,
+}
+
+/// HTTP version written in the request line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Version {
+    /// HTTP/1.0, where the connection is closed after the response unless agreed otherwise
+    Http10,
+    #[default]
+    Http11,
+}
+
+impl Version {
+    pub fn str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Version {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.str());
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -52,6 +98,10 @@ impl<'a> Header<'a> {
                 .into_iter()
                 .map(|(k, v)| (k.into_owned(), v.into_owned()))
                 .collect(),
+            no_content_length_on_empty: self.no_content_length_on_empty,
+            asterisk_form: self.asterisk_form,
+            version: self.version,
+            user_agent: self.user_agent.map(HeaderValue::into_owned),
         }
     }
 
@@ -64,6 +114,10 @@ impl<'a> Header<'a> {
                 .iter()
                 .map(|(k, v)| (k.into_borrowed(), v.into_borrowed()))
                 .collect(),
+            no_content_length_on_empty: self.no_content_length_on_empty,
+            asterisk_form: self.asterisk_form,
+            version: self.version,
+            user_agent: self.user_agent.as_ref().map(HeaderValue::into_borrowed),
         }
     }
 }
@@ -74,6 +128,33 @@ impl Display for Method {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Method {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.str());
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a, S: HeaderStorage<'a>> defmt::Format for Header<'a, S> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "{} {} ({=usize} headers)",
+            self.method,
+            self.uri,
+            self.headers.as_slice().len()
+        );
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a, T, S: HeaderStorage<'a>> defmt::Format for Request<'a, T, S> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "Request({})", self.header);
+    }
+}
+
 impl Method {
     pub fn str(&self) -> &'static str {
         match self {
@@ -90,6 +171,126 @@ impl Method {
     }
 }
 
+/// Parses a method name, which is case-sensitive
+impl core::str::FromStr for Method {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "OPTIONS" => Method::Options,
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "HEAD" => Method::Head,
+            "TRACE" => Method::Trace,
+            "CONNECT" => Method::Connect,
+            "PATCH" => Method::Patch,
+            _ => return Err(Error::UnknownMethod),
+        })
+    }
+}
+
+/// Parses the request line and headers of an incoming request, e.g. to modify and forward it
+/// with [`Request::write_to`]. Returns the header and the offset of the body in `buf`.
+///
+/// A path-only target, as sent to origin servers, is combined with the `Host` header into an
+/// `http` uri. `Host` and `User-Agent` are taken out of the headers, like when building a request.
+/// `Content-Length` and `Transfer-Encoding` are kept to tell how the body is framed, but aren't
+/// written when the request is sent with a body declaring its own length.
+///
+/// Fails with [`Error::Incomplete`] if `buf` doesn't hold the whole header yet, and with
+/// [`Error::AmbiguousFraming`] if the request has both `Transfer-Encoding` and `Content-Length`.
+pub fn parse(buf: &[u8]) -> Result<(Header<'_>, usize)> {
+    // skip empty lines before the request line, which servers should ignore
+    let start = buf
+        .iter()
+        .position(|b| !matches!(b, b'\r' | b'\n'))
+        .ok_or(Error::Incomplete)?;
+
+    let mut lines = Vec::new();
+    let mut pos = start;
+    let body_offset = loop {
+        let end = buf[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or(Error::Incomplete)?;
+        let line = &buf[pos..pos + end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        pos += end + 1;
+        if line.is_empty() {
+            break pos;
+        }
+        lines.push(core::str::from_utf8(line)?);
+    };
+    let (request_line, fields) = lines.split_first().ok_or(Error::InvalidRequestLine)?;
+
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Error::InvalidRequestLine);
+    };
+    let method: Method = method.parse()?;
+    let version = match version {
+        "HTTP/1.1" => Version::Http11,
+        "HTTP/1.0" => Version::Http10,
+        v if v.starts_with("HTTP/") => return Err(Error::UnsupportedVersion),
+        _ => return Err(Error::InvalidRequestLine),
+    };
+
+    let mut headers = Vec::with_capacity(fields.len());
+    let mut host = None;
+    let mut user_agent = None;
+    let mut framing = (false, false);
+    for field in fields {
+        // obsolete line folding is rejected, as RFC 9112 allows for requests
+        let (name, value) = field.split_once(':').ok_or(Error::InvalidHeaderKey)?;
+        let key = HeaderKey::checked(name)?;
+        let value = HeaderValue::checked(value.trim_matches([' ', '\t']).as_bytes())?;
+
+        if key.inner.eq_ignore_ascii_case("host") {
+            host.get_or_insert(value);
+        } else if key.inner.eq_ignore_ascii_case("user-agent") {
+            user_agent = Some(value);
+        } else {
+            framing.0 |= key.inner.eq_ignore_ascii_case("transfer-encoding");
+            framing.1 |= key.inner.eq_ignore_ascii_case("content-length");
+            headers.push((key, value));
+        }
+    }
+    if framing == (true, true) {
+        return Err(Error::AmbiguousFraming);
+    }
+
+    let host = || {
+        host.as_ref()
+            .and_then(|host| host.as_str().ok())
+            .ok_or(Error::InvalidUri(UriError::EmptyAuthority))
+    };
+    let (uri, asterisk_form) = match target {
+        "*" => (Uri::parse(alloc::format!("http://{}/", host()?))?, true),
+        path if path.starts_with('/') => (
+            Uri::parse(alloc::format!("http://{}{}", host()?, path))?,
+            false,
+        ),
+        uri => (Uri::parse(uri)?, false),
+    };
+
+    Ok((
+        Header {
+            method,
+            uri,
+            headers,
+            no_content_length_on_empty: false,
+            asterisk_form,
+            version,
+            user_agent,
+        },
+        body_offset,
+    ))
+}
+
 impl<'a, T> Request<'a, T> {
     pub fn new(method: Method, uri: Uri<'a>, body: T) -> Self {
         Self {
@@ -97,64 +298,247 @@ impl<'a, T> Request<'a, T> {
                 method,
                 uri,
                 headers: Vec::new(),
+                no_content_length_on_empty: false,
+                asterisk_form: false,
+                version: Version::Http11,
+                user_agent: Some(DEFAULT_USER_AGENT),
             },
             body,
         }
     }
 }
 
-impl<'a, T> Request<'a, T> {
+impl<'a, T, S> Request<'a, T, S> {
+    /// Replaces the body, keeping the parsed uri and headers,
+    /// e.g. to send a new payload to the same endpoint
+    pub fn with_body<U>(self, body: U) -> Request<'a, U, S> {
+        Request {
+            header: self.header,
+            body,
+        }
+    }
+}
+
+impl<'a, S: Clone> Header<'a, S> {
+    /// Creates a request with a copy of this header, so it can be built once and sent
+    /// with a different body each time
+    pub fn clone_into_request<T>(&self, body: T) -> Request<'a, T, S> {
+        Request {
+            header: self.clone(),
+            body,
+        }
+    }
+}
+
+/// Rejects headers that would corrupt the message, like values smuggling in another header
+pub(crate) fn check_header(name: &HeaderKey, value: &HeaderValue) -> Result<()> {
+    if !name.is_valid() {
+        return Err(Error::InvalidHeaderKey);
+    }
+    if !value.is_valid() {
+        return Err(Error::InvalidHeaderValue);
+    }
+    Ok(())
+}
+
+pub(crate) fn write_header_value<W: Write>(
+    name: &HeaderKey,
+    value: &HeaderValue,
+    w: &mut W,
+) -> Result<()>
+where
+    crate::error::Error: From<<W as ErrorType>::Error>,
+{
+    check_header(name, value)?;
+
+    write!(w, "{}: ", name)?;
+    w.write_all(value.as_ref())?;
+    write!(w, "\r\n")?;
+    Ok(())
+}
+
+/// Writes a message with the given body, letting `write_header` write the header block with
+/// the content type and content length headers describing the body appended.
+/// The body itself is left out unless `with_body` is set.
+pub(crate) fn write_message<W: Write, B: ToRequestBody>(
+    mut w: W,
+    body: &B,
+    with_body: bool,
+    write_header: impl FnOnce(&mut W, &[(&HeaderKey, &HeaderValue)]) -> Result<()>,
+) -> Result<()>
+where
+    crate::error::Error: From<<W as ErrorType>::Error>,
+{
+    // If there is no content type, we can just write the header and be done
+    let ct = if let Some(ct) = body.content_type() {
+        ct
+    } else {
+        write_header(&mut w, &[])?;
+        return Ok(());
+    };
+
+    let mut buffered = None;
+
+    // If the content length is known, we can write the body directly to the writer
+    let cl = if let Some(cl) = body.content_length() {
+        cl
+    } else {
+        let mut body_inner = Vec::new();
+        body.write_body::<&mut Vec<u8>>(body_inner.as_mut())?;
+        let cl = body_inner.len();
+        buffered = Some(body_inner);
+        cl
+    };
+
+    write_header(
+        &mut w,
+        &[
+            (&crate::header::CONTENT_TYPE, &ct),
+            (
+                &crate::header::CONTENT_LENGTH,
+                &crate::writer::IntBuffer::new().format(cl).into(),
+            ),
+        ],
+    )?;
+
+    if !with_body {
+        return Ok(());
+    }
+
+    if let Some(b) = buffered {
+        w.write_all(&b)?;
+    } else {
+        #[cfg(not(feature = "strict"))]
+        body.write_body(&mut w)?;
+
+        // verify that the body matches the content length it declared
+        #[cfg(feature = "strict")]
+        {
+            let mut counter = crate::writer::CountingWriter::new(&mut w);
+            body.write_body(&mut counter)?;
+            if counter.count() != cl {
+                return Err(Error::ContentLengthMismatch {
+                    declared: cl,
+                    actual: counter.count(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<'a, S: HeaderStorage<'a>> Header<'a, S> {
+    /// The target written in the request line
+    fn request_target(&self) -> &str {
+        match self.asterisk_form {
+            true => "*",
+            false => self.uri.path_and_query(),
+        }
+    }
+
+    /// The `Host` written from the uri, `None` for a uri without authority as created by
+    /// [`Uri::parse_origin_form`], in which case a `Host` header set on the request is sent
+    fn uri_host(&self) -> Option<HeaderValue<'_>> {
+        Some(self.uri.authority())
+            .filter(|authority| !authority.is_empty())
+            .map(HeaderValue::from)
+    }
+
+    /// The headers set on the request, without those written from the fields, like the `Host`
+    /// unless `host_from_uri` is false. The `Content-Length` and `Transfer-Encoding` of a parsed
+    /// request are left out when the body declares its own length, so it's never framed twice.
+    fn own_headers<'h>(
+        &'h self,
+        host_from_uri: bool,
+        extra_headers: &[(&HeaderKey, &HeaderValue)],
+    ) -> impl Iterator<Item = &'h (HeaderKey<'a>, HeaderValue<'a>)> {
+        let framed_by_body = extra_headers
+            .iter()
+            .any(|(key, _)| *key == &crate::header::CONTENT_LENGTH);
+
+        self.headers
+            .as_slice()
+            .iter()
+            .filter(|(key, _)| key.ne(&crate::header::USER_AGENT))
+            .filter(move |(key, _)| !host_from_uri || key.ne(&crate::header::HOST))
+            .filter(move |(key, _)| {
+                !(framed_by_body
+                    && (key.inner.eq_ignore_ascii_case("content-length")
+                        || key.inner.eq_ignore_ascii_case("transfer-encoding")))
+            })
+    }
+
+    /// The headers describing the body, without them for an empty body if so configured.
+    /// A `Content-Type` set on the request takes precedence over the one of the body.
+    fn body_headers<'h>(
+        &self,
+        extra_headers: &'h [(&'h HeaderKey<'h>, &'h HeaderValue<'h>)],
+    ) -> impl Iterator<Item = &'h (&'h HeaderKey<'h>, &'h HeaderValue<'h>)> {
+        let empty = extra_headers
+            .iter()
+            .any(|(key, value)| *key == &crate::header::CONTENT_LENGTH && value.as_ref() == b"0");
+        let extra_headers = match self.no_content_length_on_empty && empty {
+            true => &[],
+            false => extra_headers,
+        };
+
+        let has_content_type = self
+            .headers
+            .as_slice()
+            .iter()
+            .any(|(key, _)| key.inner.eq_ignore_ascii_case("content-type"));
+        extra_headers
+            .iter()
+            .filter(move |(key, _)| !(has_content_type && *key == &crate::header::CONTENT_TYPE))
+    }
+
     fn write_header<W: Write>(
         &self,
-        mut w: W,
+        w: W,
         extra_headers: &[(&HeaderKey, &HeaderValue)],
     ) -> Result<(), Error>
     where
         crate::error::Error: From<<W as ErrorType>::Error>,
     {
-        fn write_header_value<W: Write>(
-            name: &HeaderKey,
-            value: &HeaderValue,
-            w: &mut W,
-        ) -> Result<()>
-        where
-            crate::error::Error: From<<W as ErrorType>::Error>,
-        {
-            write!(w, "{}: ", name)?;
-            w.write_all(value.as_ref())?;
-            write!(w, "\r\n")?;
-            Ok(())
-        }
+        self.write_header_with(w, extra_headers, write_header_value)
+    }
 
+    /// Writes the header block, writing each field with `write_field`
+    fn write_header_with<W: Write>(
+        &self,
+        mut w: W,
+        extra_headers: &[(&HeaderKey, &HeaderValue)],
+        write_field: impl Fn(&HeaderKey, &HeaderValue, &mut W) -> Result<()>,
+    ) -> Result<(), Error>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
         write!(
             w,
-            "{} {} HTTP/1.1\r\n",
-            &self.header.method,
-            self.header.uri.path_and_query()
+            "{} {} {}\r\n",
+            self.method,
+            self.request_target(),
+            self.version
         )?;
 
         // write host field
-        write_header_value(
-            &crate::header::HOST,
-            &self.header.uri.authority().into(),
-            &mut w,
-        )?;
+        let host = self.uri_host();
+        if let Some(host) = &host {
+            write_field(&crate::header::HOST, host, &mut w)?;
+        }
 
         // write user agent field
-        write_header_value(&crate::header::USER_AGENT, &USER_AGENT, &mut w)?;
+        if let Some(user_agent) = &self.user_agent {
+            write_field(&crate::header::USER_AGENT, user_agent, &mut w)?;
+        }
 
-        for (name, value) in self
-            .header
-            .headers
-            .iter()
-            .filter(|(key, _)| key.ne(&crate::header::USER_AGENT))
-            .filter(|(key, _)| key.ne(&crate::header::HOST))
-        {
-            write_header_value(name, value, &mut w)?;
+        for (name, value) in self.own_headers(host.is_some(), extra_headers) {
+            write_field(name, value, &mut w)?;
         }
 
-        for (name, value) in extra_headers {
-            write_header_value(name, value, &mut w)?;
+        for (name, value) in self.body_headers(extra_headers) {
+            write_field(name, value, &mut w)?;
         }
 
         write!(w, "\r\n")?;
@@ -163,20 +547,181 @@ impl<'a, T> Request<'a, T> {
     }
 }
 
+#[cfg(feature = "async")]
+async fn write_header_value_async<W: embedded_io_async::Write>(
+    name: &HeaderKey<'_>,
+    value: &HeaderValue<'_>,
+    w: &mut W,
+) -> Result<()>
+where
+    crate::error::Error: From<<W as embedded_io_async::ErrorType>::Error>,
+{
+    check_header(name, value)?;
+
+    w.write_all(name.inner.as_bytes()).await?;
+    w.write_all(b": ").await?;
+    w.write_all(value.as_ref()).await?;
+    w.write_all(b"\r\n").await?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+impl<'a, S: HeaderStorage<'a>> Header<'a, S> {
+    /// Async version of `write_header`
+    async fn write_header_async<W: embedded_io_async::Write>(
+        &self,
+        w: &mut W,
+        extra_headers: &[(&HeaderKey<'_>, &HeaderValue<'_>)],
+    ) -> Result<(), Error>
+    where
+        crate::error::Error: From<<W as embedded_io_async::ErrorType>::Error>,
+    {
+        w.write_all(self.method.str().as_bytes()).await?;
+        w.write_all(b" ").await?;
+        w.write_all(self.request_target().as_bytes()).await?;
+        w.write_all(b" ").await?;
+        w.write_all(self.version.str().as_bytes()).await?;
+        w.write_all(b"\r\n").await?;
+
+        let host = self.uri_host();
+        if let Some(host) = &host {
+            write_header_value_async(&crate::header::HOST, host, w).await?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            write_header_value_async(&crate::header::USER_AGENT, user_agent, w).await?;
+        }
+
+        for (name, value) in self.own_headers(host.is_some(), extra_headers) {
+            write_header_value_async(name, value, w).await?;
+        }
+
+        for (name, value) in self.body_headers(extra_headers) {
+            write_header_value_async(name, value, w).await?;
+        }
+
+        w.write_all(b"\r\n").await?;
+
+        Ok(())
+    }
+}
+
+/// Adaptor writing into a `core::fmt::Formatter`, rendering bytes that aren't valid utf8 lossily
+struct FmtWriter<'a, 'b>(&'a mut core::fmt::Formatter<'b>);
+
+impl ErrorType for FmtWriter<'_, '_> {
+    type Error = Error;
+}
+
+impl Write for FmtWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        for chunk in buf.utf8_chunks() {
+            self.0
+                .write_str(chunk.valid())
+                .map_err(|_| Error::FmtError)?;
+            if !chunk.invalid().is_empty() {
+                self.0.write_str("\u{fffd}").map_err(|_| Error::FmtError)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a header field for display without validating it, escaping the CR, LF and NUL
+/// bytes that [`write_header_value`] would reject
+fn write_header_escaped<W: Write>(name: &HeaderKey, value: &HeaderValue, w: &mut W) -> Result<()>
+where
+    crate::error::Error: From<<W as ErrorType>::Error>,
+{
+    write_escaped(name.inner.as_bytes(), w)?;
+    w.write_all(b": ")?;
+    write_escaped(value.as_ref(), w)?;
+    w.write_all(b"\r\n")?;
+    Ok(())
+}
+
+fn write_escaped<W: Write>(bytes: &[u8], w: &mut W) -> Result<()>
+where
+    crate::error::Error: From<<W as ErrorType>::Error>,
+{
+    let mut pos = 0;
+    for part in bytes.split(|b| matches!(b, b'\r' | b'\n' | b'\0')) {
+        // written in runs, so multi-byte utf8 characters stay whole
+        w.write_all(part)?;
+        pos += part.len();
+        match bytes.get(pos) {
+            Some(b'\r') => w.write_all(b"\\r")?,
+            Some(b'\n') => w.write_all(b"\\n")?,
+            Some(_) => w.write_all(b"\\0")?,
+            None => {}
+        }
+        pos += 1;
+    }
+    Ok(())
+}
+
+/// Renders the header block as it is written on the wire.
+/// Header fields that would be rejected when writing are rendered with CR, LF and NUL escaped.
+impl<'a, S: HeaderStorage<'a>> Display for Header<'a, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write_header_with(FmtWriter(f), &[], write_header_escaped)
+            .map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Renders the request as it is written on the wire, see [`Header`] for the header block.
+/// Bodies that aren't valid utf8 are rendered as `<N bytes>`, and bodies that fail
+/// to serialize as `<body error>`.
+impl<'a, T: ToRequestBody, S: HeaderStorage<'a>> Display for Request<'a, T, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Some(content_type) = self.body.content_type() else {
+            return Display::fmt(&self.header, f);
+        };
+
+        let mut body = Vec::new();
+        if self.body.write_body(&mut body).is_err() {
+            Display::fmt(&self.header, f)?;
+            return f.write_str("<body error>");
+        }
+
+        // the body is serialized already, so only the formatter can fail from here on
+        let body = RawBody {
+            bytes: &body,
+            content_type,
+        };
+        write_message(FmtWriter(f), &body, false, |w, extra_headers| {
+            self.header
+                .write_header_with(w, extra_headers, write_header_escaped)
+        })
+        .map_err(|_| core::fmt::Error)?;
+
+        match core::str::from_utf8(body.bytes) {
+            Ok(body) => f.write_str(body),
+            Err(_) => write!(f, "<{} bytes>", body.bytes.len()),
+        }
+    }
+}
+
 #[cfg(feature = "serde_json")]
-impl<'a, T: Serialize> Request<'a, T> {
+impl<'a, T: Serialize, S: HeaderStorage<'a>> Request<'a, T, S> {
     pub fn write_json_to<W: Write>(&self, mut w: W) -> Result<()>
     where
         crate::error::Error: From<<W as ErrorType>::Error>,
     {
         let body = serde_json::to_string(&self.body)?;
 
-        let mut b = itoa::Buffer::new();
+        let mut b = crate::writer::IntBuffer::new();
         let cl = b.format(body.len());
-        self.write_header(
+        self.header.write_header(
             &mut w,
             &[
-                (&crate::header::CONTENT_TYPE, &crate::mime::APPLICATION_JSON),
+                (
+                    &crate::header::CONTENT_TYPE,
+                    &crate::mime::APPLICATION_JSON.into(),
+                ),
                 (&crate::header::CONTENT_LENGTH, &cl.into()),
             ],
         )?;
@@ -186,8 +731,74 @@ impl<'a, T: Serialize> Request<'a, T> {
         Ok(())
     }
 }
+#[cfg(feature = "serde_json_core")]
+impl<'a, T: serde::Serialize, S: HeaderStorage<'a>> Request<'a, T, S> {
+    /// Serializes the body as JSON into `buf` and writes the request, without allocating.
+    ///
+    /// Returns `Error::BufferTooSmall` if the body doesn't fit in `buf`. The size needed
+    /// isn't known in that case, so it is reported as one more than the size of `buf`.
+    pub fn write_json_core_to<W: Write>(&self, mut w: W, buf: &mut [u8]) -> Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        let len = serde_json_core::to_slice(&self.body, buf).map_err(|e| match e {
+            serde_json_core::ser::Error::BufferFull => {
+                Error::BufferTooSmall(buf.len(), buf.len() + 1)
+            }
+            e => e.into(),
+        })?;
+
+        let mut b = crate::writer::IntBuffer::new();
+        let cl = b.format(len);
+        self.header.write_header(
+            &mut w,
+            &[
+                (
+                    &crate::header::CONTENT_TYPE,
+                    &crate::mime::APPLICATION_JSON.into(),
+                ),
+                (&crate::header::CONTENT_LENGTH, &cl.into()),
+            ],
+        )?;
+
+        w.write_all(&buf[..len])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "serde_json", feature = "async"))]
+impl<'a, T: Serialize, S: HeaderStorage<'a>> Request<'a, T, S> {
+    /// Async version of [`Request::write_json_to`]
+    pub async fn write_json_to_async<W: embedded_io_async::Write>(&self, mut w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as embedded_io_async::ErrorType>::Error>,
+    {
+        let body = serde_json::to_string(&self.body)?;
+
+        let mut b = crate::writer::IntBuffer::new();
+        let cl = b.format(body.len());
+        self.header
+            .write_header_async(
+                &mut w,
+                &[
+                    (
+                        &crate::header::CONTENT_TYPE,
+                        &crate::mime::APPLICATION_JSON.into(),
+                    ),
+                    (&crate::header::CONTENT_LENGTH, &cl.into()),
+                ],
+            )
+            .await?;
+
+        w.write_all(body.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
 #[cfg(feature = "serde_json")]
-impl<'a, T: Serialize> Request<'a, T> {
+impl<'a, T: Serialize, S: HeaderStorage<'a>> Request<'a, T, S> {
     pub fn to_json_vec(&self) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         self.write_json_to(&mut buf)?;
@@ -195,57 +806,158 @@ impl<'a, T: Serialize> Request<'a, T> {
     }
 }
 
-impl<'a, T: ToRequestBody> Request<'a, T> {
-    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()>
+#[cfg(feature = "cbor")]
+impl<'a, T: serde::Serialize, S: HeaderStorage<'a>> Request<'a, T, S> {
+    /// Serializes the body as CBOR and writes the request with `Content-Type: application/cbor`
+    pub fn write_cbor_to<W: Write>(&self, mut w: W) -> Result<()>
     where
         crate::error::Error: From<<W as ErrorType>::Error>,
     {
-        // If there is no content type, we can just write the header and be done
-        let ct = if let Some(ct) = self.body.content_type() {
-            ct
-        } else {
-            self.write_header(&mut w, &[])?;
-            return Ok(());
-        };
-
-        let mut body = None;
-
-        // If the content length is known, we can write the body directly to the writer
-        let cl = if let Some(cl) = self.body.content_length() {
-            cl
-        } else {
-            let mut body_inner = Vec::new();
-            self.body.write_body::<&mut Vec<u8>>(body_inner.as_mut())?;
-            let cl = body_inner.len();
-            body = Some(body_inner);
-            cl
-        };
+        let mut body = Vec::new();
+        ciborium::into_writer(&self.body, &mut body)?;
 
-        self.write_header(
+        let mut b = crate::writer::IntBuffer::new();
+        let cl = b.format(body.len());
+        self.header.write_header(
             &mut w,
             &[
-                (&crate::header::CONTENT_TYPE, &ct),
                 (
-                    &crate::header::CONTENT_LENGTH,
-                    &itoa::Buffer::new().format(cl).into(),
+                    &crate::header::CONTENT_TYPE,
+                    &crate::mime::APPLICATION_CBOR.into(),
                 ),
+                (&crate::header::CONTENT_LENGTH, &cl.into()),
             ],
         )?;
 
-        if let Some(b) = body {
-            w.write_all(&b)?;
-        } else {
-            self.body.write_body(&mut w)?;
-        }
+        w.write_all(&body)?;
 
         Ok(())
     }
 
+    pub fn to_cbor_vec(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_cbor_to(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl<'a, T: ToRequestBody, S: HeaderStorage<'a>> Request<'a, T, S> {
+    /// Writes the request. `Content-Type` and `Content-Length` are only sent for bodies with a
+    /// content type, so a request with the `()` body has neither, whatever the method.
+    /// TRACE requests must not have a body (RFC 9110), this is checked in debug builds.
+    pub fn write_to<W: Write>(&self, w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        self.debug_check_trace();
+        write_message(w, &self.body, true, |w, extra_headers| {
+            self.header.write_header(w, extra_headers)
+        })
+    }
+
+    /// Writes the header block only, including the headers describing the body.
+    /// Used together with `Expect: 100-continue` to wait for the server before sending the body
+    /// with [`Request::write_body_to`].
+    pub fn write_header_only<W: Write>(&self, w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        self.debug_check_trace();
+        write_message(w, &self.body, false, |w, extra_headers| {
+            self.header.write_header(w, extra_headers)
+        })
+    }
+
+    /// Writes the body only, following [`Request::write_header_only`]
+    pub fn write_body_to<W: Write>(&self, w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        self.body.write_body(w)
+    }
+
     pub fn to_vec(&self) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         self.write_to(&mut buf)?;
         Ok(buf)
     }
+
+    /// Number of bytes [`Request::write_to`] writes, e.g. to size a [`crate::writer::SliceWriter`].
+    /// Fails with `Error::UnknownContentLength` for bodies not declaring their content length.
+    pub fn serialized_len(&self) -> Result<usize> {
+        let body_len = match self.body.content_type() {
+            Some(_) => self
+                .body
+                .content_length()
+                .ok_or(Error::UnknownContentLength)?,
+            None => 0,
+        };
+
+        let mut counter = crate::writer::CountingWriter::default();
+        self.write_header_only(&mut counter)?;
+        Ok(counter.count() + body_len)
+    }
+
+    fn debug_check_trace(&self) {
+        debug_assert!(
+            self.header.method != Method::Trace || self.body.content_type().is_none(),
+            "TRACE requests must not have a body"
+        );
+    }
+
+    /// Async version of [`Request::write_to`]. Bodies that are one slice are streamed directly,
+    /// others are buffered first since [`ToRequestBody`] writes synchronously.
+    #[cfg(feature = "async")]
+    pub async fn write_to_async<W: embedded_io_async::Write>(&self, mut w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as embedded_io_async::ErrorType>::Error>,
+    {
+        self.debug_check_trace();
+        let ct = if let Some(ct) = self.body.content_type() {
+            ct
+        } else {
+            return self.header.write_header_async(&mut w, &[]).await;
+        };
+
+        let buffered;
+        let body = match self.body.body_slice() {
+            Some(body) => body,
+            None => {
+                let mut body_inner = Vec::new();
+                self.body.write_body::<&mut Vec<u8>>(body_inner.as_mut())?;
+                buffered = body_inner;
+                buffered.as_slice()
+            }
+        };
+
+        // verify that the body matches the content length it declared
+        #[cfg(feature = "strict")]
+        match self.body.content_length() {
+            Some(cl) if cl != body.len() => {
+                return Err(Error::ContentLengthMismatch {
+                    declared: cl,
+                    actual: body.len(),
+                })
+            }
+            _ => {}
+        }
+
+        let mut b = crate::writer::IntBuffer::new();
+        let cl = b.format(body.len());
+        self.header
+            .write_header_async(
+                &mut w,
+                &[
+                    (&crate::header::CONTENT_TYPE, &ct),
+                    (&crate::header::CONTENT_LENGTH, &cl.into()),
+                ],
+            )
+            .await?;
+
+        w.write_all(body).await?;
+
+        Ok(())
+    }
 }
 
 pub trait ToRequestBody {
@@ -258,6 +970,12 @@ pub trait ToRequestBody {
     fn content_length(&self) -> Option<usize> {
         None
     }
+
+    /// The body as one slice, if it is stored as one.
+    /// Lets the async writers send the body without buffering it first.
+    fn body_slice(&self) -> Option<&[u8]> {
+        None
+    }
 }
 
 impl<B: ToRequestBody> ToRequestBody for &B {
@@ -268,26 +986,30 @@ impl<B: ToRequestBody> ToRequestBody for &B {
         (*self).write_body(w)
     }
 
-    fn content_type(&self) -> Option<HeaderValue> {
+    fn content_type(&self) -> Option<HeaderValue<'_>> {
         (*self).content_type()
     }
 
     fn content_length(&self) -> Option<usize> {
         (*self).content_length()
     }
-}
+
+    fn body_slice(&self) -> Option<&[u8]> {
+        (*self).body_slice()
+    }
+}
 
 impl ToRequestBody for () {
     fn write_body<W: Write>(&self, _w: W) -> Result<()> {
         Ok(())
     }
 
-    fn content_type(&self) -> Option<HeaderValue> {
+    fn content_type(&self) -> Option<HeaderValue<'_>> {
         None
     }
 }
 
-impl<'body> ToRequestBody for &'body str {
+impl ToRequestBody for &str {
     fn write_body<W: Write>(&self, mut w: W) -> Result<()>
     where
         crate::error::Error: From<<W as ErrorType>::Error>,
@@ -295,16 +1017,20 @@ impl<'body> ToRequestBody for &'body str {
         Ok(w.write_all(self.as_bytes())?)
     }
 
-    fn content_type(&self) -> Option<HeaderValue> {
-        Some(crate::mime::TEXT_PLAIN_UTF_8.into_borrowed())
+    fn content_type(&self) -> Option<HeaderValue<'_>> {
+        Some(crate::mime::TEXT_PLAIN_UTF_8.into())
     }
 
     fn content_length(&self) -> Option<usize> {
         Some(self.len())
     }
+
+    fn body_slice(&self) -> Option<&[u8]> {
+        Some(self.as_bytes())
+    }
 }
 
-impl<'body> ToRequestBody for &'body [u8] {
+impl ToRequestBody for &[u8] {
     fn write_body<W: Write>(&self, mut w: W) -> Result<()>
     where
         crate::error::Error: From<<W as ErrorType>::Error>,
@@ -312,64 +1038,391 @@ impl<'body> ToRequestBody for &'body [u8] {
         Ok(w.write_all(self)?)
     }
 
-    fn content_type(&self) -> Option<HeaderValue> {
-        Some(crate::mime::APPLICATION_OCTET_STREAM.into_borrowed())
+    fn content_type(&self) -> Option<HeaderValue<'_>> {
+        Some(crate::mime::APPLICATION_OCTET_STREAM.into())
     }
 
     fn content_length(&self) -> Option<usize> {
         Some(self.len())
     }
+
+    fn body_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
 }
 
-pub struct RequestBuilder<'a> {
-    headers: Vec<(HeaderKey<'a>, HeaderValue<'a>)>,
+/// Bytes sent with a content type only known at runtime, e.g. when relaying a payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBody<'a> {
+    pub bytes: &'a [u8],
+    pub content_type: HeaderValue<'a>,
+}
+
+impl ToRequestBody for RawBody<'_> {
+    fn write_body<W: Write>(&self, mut w: W) -> Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        Ok(w.write_all(self.bytes)?)
+    }
+
+    fn content_type(&self) -> Option<HeaderValue<'_>> {
+        Some(self.content_type.into_borrowed())
+    }
+
+    fn content_length(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+
+    fn body_slice(&self) -> Option<&[u8]> {
+        Some(self.bytes)
+    }
+}
+
+pub struct RequestBuilder<'a, S = HeaderVec<'a>> {
+    headers: S,
     method: Method,
     uri: Uri<'a>,
+    no_content_length_on_empty: bool,
+    asterisk_form: bool,
+    version: Version,
+    user_agent: HeaderValue<'a>,
+    /// first error raised while building, returned by the fallible terminals
+    error: Option<Error>,
 }
 
 impl<'a> RequestBuilder<'a> {
     pub fn get<U: TryInto<Uri<'a>>>(uri: U) -> Result<Self, U::Error> {
-        Ok(Self {
-            headers: Vec::new(),
-            method: Method::Get,
-            uri: uri.try_into()?,
-        })
+        Self::with_storage(Method::Get, uri, Vec::new())
     }
 
     pub fn post<U: TryInto<Uri<'a>>>(uri: U) -> Result<Self, U::Error> {
-        Ok(Self {
-            headers: Vec::new(),
-            method: Method::Post,
-            uri: uri.try_into()?,
-        })
+        Self::with_storage(Method::Post, uri, Vec::new())
     }
 
     pub fn put<U: TryInto<Uri<'a>>>(uri: U) -> Result<Self, U::Error> {
+        Self::with_storage(Method::Put, uri, Vec::new())
+    }
+
+    /// Creates a builder with room for `capacity` headers, avoiding reallocations
+    /// while they are added
+    pub fn with_capacity<U: TryInto<Uri<'a>>>(
+        method: Method,
+        uri: U,
+        capacity: usize,
+    ) -> Result<Self, U::Error> {
+        Self::with_storage(method, uri, Vec::with_capacity(capacity))
+    }
+
+    /// Reserves room for `additional` more headers
+    pub fn reserve(mut self, additional: usize) -> Self {
+        self.headers.reserve(additional);
+        self
+    }
+
+    /// Creates a conditional GET revalidating `prev`, sending its `ETag` as `If-None-Match`
+    /// and its `Last-Modified` as `If-Modified-Since`. Headers `prev` didn't have are left out.
+    /// A `304` answer is recognized by [`Response::is_not_modified`].
+    pub fn conditional_from<U: TryInto<Uri<'a>>>(uri: U, prev: &mut Response<'_>) -> Result<Self>
+    where
+        Error: From<U::Error>,
+    {
+        let mut builder = Self::get(uri)?;
+        for (from, to) in [
+            (&crate::header::ETAG, &crate::header::IF_NONE_MATCH),
+            (
+                &crate::header::LAST_MODIFIED,
+                &crate::header::IF_MODIFIED_SINCE,
+            ),
+        ] {
+            match prev.header_value(from.inner.as_ref()) {
+                Ok(value) => {
+                    builder = builder.insert_header((to.clone(), value.into_owned().into()))
+                }
+                Err(ResponseError::HeaderNotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Creates an `OPTIONS * HTTP/1.1` request, asking `authority` about the server as a whole
+    pub fn options_asterisk(authority: &str) -> Result<Self> {
+        let uri = Uri::parse(alloc::format!("http://{}/", authority))?;
+        let mut builder = Self::with_storage(Method::Options, uri, Vec::new())?;
+        builder.asterisk_form = true;
+        Ok(builder)
+    }
+
+    /// Creates the request with `body`, or returns the first error raised while building it,
+    /// see [`RequestBuilder::build_with_body`]
+    pub fn body<T>(self, body: T) -> Result<Request<'a, T>> {
+        self.build_with_body(body)
+    }
+}
+
+impl<'a, S: HeaderStorage<'a>> RequestBuilder<'a, S> {
+    /// Creates a builder storing the headers in `headers`, e.g. a fixed capacity `HeaderMap`
+    pub fn with_storage<U: TryInto<Uri<'a>>>(
+        method: Method,
+        uri: U,
+        headers: S,
+    ) -> Result<Self, U::Error> {
         Ok(Self {
-            headers: Vec::new(),
-            method: Method::Put,
+            headers,
+            method,
             uri: uri.try_into()?,
+            no_content_length_on_empty: false,
+            asterisk_form: false,
+            version: Version::Http11,
+            user_agent: DEFAULT_USER_AGENT,
+            error: None,
         })
     }
 
+    /// Sets a header, replacing any header with the same name. If the key isn't a valid token or
+    /// the header storage is full, the error is returned by [`RequestBuilder::build_with_body`].
     pub fn insert_header(mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> Self {
-        self.headers.push(header);
+        let res = match header.0.is_valid() {
+            true => self.headers.insert(header),
+            false => Err(Error::InvalidHeaderKey),
+        };
+        self.record(res);
         self
     }
 
-    pub fn body<T>(self, body: T) -> Request<'a, T> {
-        Request {
+    /// Appends a header, keeping any header with the same name. If the key isn't a valid token or
+    /// the header storage is full, the error is returned by [`RequestBuilder::build_with_body`].
+    pub fn append_header(mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> Self {
+        let res = match header.0.is_valid() {
+            true => self.headers.push(header),
+            false => Err(Error::InvalidHeaderKey),
+        };
+        self.record(res);
+        self
+    }
+
+    /// Appends a header given as bytes, written verbatim in insertion order with the other headers.
+    /// The value may hold any bytes but CR, LF and NUL, the key must still be a token.
+    /// Invalid headers are reported by [`RequestBuilder::build_with_body`].
+    pub fn raw_header(mut self, key: &'static [u8], value: &'a [u8]) -> Self {
+        match core::str::from_utf8(key) {
+            Ok(key) => self.append_header((HeaderKey::from_unchecked(key), value.into())),
+            Err(_) => {
+                self.record(Err(Error::InvalidHeaderKey));
+                self
+            }
+        }
+    }
+
+    /// Keeps the first error for the fallible terminals
+    fn record(&mut self, res: Result<()>) {
+        if let Err(e) = res {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    /// Sets the headers needed to upgrade the connection to a WebSocket (RFC 6455).
+    /// `key` is the base64 encoded 16 byte nonce sent as `Sec-WebSocket-Key`.
+    pub fn websocket_upgrade(self, key: &'a str) -> Self {
+        self.insert_header((
+            crate::header::UPGRADE.clone(),
+            HeaderValue::from_static(b"websocket"),
+        ))
+        .insert_header((
+            crate::header::CONNECTION.clone(),
+            HeaderValue::from_static(b"Upgrade"),
+        ))
+        .insert_header((
+            crate::header::SEC_WEBSOCKET_VERSION.clone(),
+            HeaderValue::from_static(b"13"),
+        ))
+        .insert_header((crate::header::SEC_WEBSOCKET_KEY.clone(), key.into()))
+    }
+
+    /// Sets the HTTP version of the request, e.g. for legacy servers only speaking HTTP/1.0
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the `User-Agent`, which is [`DEFAULT_USER_AGENT`] otherwise
+    pub fn user_agent(mut self, user_agent: impl Into<HeaderValue<'a>>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Leaves out `Content-Type` and `Content-Length` when the body turns out to be empty,
+    /// instead of sending `Content-Length: 0`
+    pub fn no_content_length_on_empty(mut self) -> Self {
+        self.no_content_length_on_empty = true;
+        self
+    }
+
+    /// Asks the server to close the connection after the response with `Connection: close`,
+    /// replacing any `Connection` header set before
+    pub fn connection_close(self) -> Self {
+        self.insert_header((
+            crate::header::CONNECTION.clone(),
+            HeaderValue::from_static(b"close"),
+        ))
+    }
+
+    /// Asks the server to keep the connection open. HTTP/1.1 does so by default, so any
+    /// `Connection` header is removed, while HTTP/1.0 needs `Connection: keep-alive`.
+    /// Call this after [`RequestBuilder::version`].
+    pub fn connection_keep_alive(mut self) -> Self {
+        match self.version {
+            Version::Http10 => self.insert_header((
+                crate::header::CONNECTION.clone(),
+                HeaderValue::from_static(b"keep-alive"),
+            )),
+            Version::Http11 => {
+                self.headers
+                    .retain(|(key, _)| !key.inner.eq_ignore_ascii_case("connection"));
+                self
+            }
+        }
+    }
+
+    /// Sets the `Content-Type` header, replacing the content type of the body
+    pub fn content_type(self, mime: Mime<'a>) -> Self {
+        self.insert_header((crate::header::CONTENT_TYPE.clone(), mime.into()))
+    }
+
+    /// Sets the `Content-Type` header from the extension of `filename`, using
+    /// `application/octet-stream` if the extension is missing or unknown
+    pub fn content_type_for_file(self, filename: &str) -> Self {
+        let mime = filename
+            .rsplit_once('.')
+            .and_then(|(_, ext)| crate::mime::from_extension(ext))
+            .unwrap_or(crate::mime::APPLICATION_OCTET_STREAM);
+        self.content_type(mime)
+    }
+
+    /// Sets `Accept: application/json`
+    pub fn accept_json(self) -> Self {
+        self.insert_header((
+            crate::header::ACCEPT.clone(),
+            crate::mime::APPLICATION_JSON.into(),
+        ))
+    }
+
+    /// Sets the `If-Modified-Since` header, so the server can answer `304 Not Modified`
+    pub fn if_modified_since(self, date: chrono::DateTime<chrono::Utc>) -> Self {
+        self.insert_header((crate::header::IF_MODIFIED_SINCE.clone(), date.into()))
+    }
+
+    /// Sets the `If-Match` header to the entity tag of the resource as last received, so the server
+    /// answers `412 Precondition Failed` instead of applying e.g. a `PUT` to a changed resource.
+    /// An `etag` containing CR or LF fails when building the request.
+    pub fn if_match(self, etag: &'a str) -> Self {
+        self.insert_header((crate::header::IF_MATCH.clone(), etag.into()))
+    }
+
+    /// Sets the `If-Unmodified-Since` header, so the server answers `412 Precondition Failed`
+    /// instead of e.g. overwriting a resource changed since `date`
+    pub fn if_unmodified_since(self, date: chrono::DateTime<chrono::Utc>) -> Self {
+        self.insert_header((crate::header::IF_UNMODIFIED_SINCE.clone(), date.into()))
+    }
+
+    /// Sets the `Date` header, formatted as an HTTP date like `Sun, 06 Nov 1994 08:49:37 GMT`
+    pub fn date(self, date: chrono::DateTime<chrono::Utc>) -> Self {
+        self.insert_header((crate::header::DATE.clone(), date.into()))
+    }
+
+    /// Sets `Expect: 100-continue`, asking the server to confirm before the body is sent
+    pub fn expect_continue(self) -> Self {
+        self.insert_header((
+            crate::header::EXPECT.clone(),
+            HeaderValue::from_static(b"100-continue"),
+        ))
+    }
+
+    /// Sets `Max-Forwards`, limiting how many proxies may forward a `TRACE` or `OPTIONS` request
+    pub fn max_forwards(self, n: u32) -> Self {
+        self.insert_header((crate::header::MAX_FORWARDS.clone(), n.into()))
+    }
+
+    /// Sets the `Accept` header from a list of media types with optional quality values, in order.
+    /// Quality values are clamped to 0..=1 and rounded to the three decimals allowed, and omitted
+    /// when 1. Only a quality of 0 is sent as `q=0`, meaning "not acceptable", smaller positive
+    /// values are sent as `q=0.001`. A NaN quality fails when building the request.
+    pub fn accept(mut self, media_types: &[(&str, Option<f32>)]) -> Self {
+        let mut value = alloc::string::String::new();
+
+        for (idx, (media_type, q)) in media_types.iter().enumerate() {
+            if idx > 0 {
+                value.push_str(", ");
+            }
+            value.push_str(media_type);
+
+            let Some(q) = q else {
+                continue;
+            };
+            if q.is_nan() {
+                self.record(Err(Error::InvalidHeaderValue));
+                return self;
+            }
+            // round to thousandths without the float functions missing from core
+            let q = q.clamp(0.0, 1.0);
+            let thousandths = match (q * 1000.0 + 0.5) as u16 {
+                0 if q > 0.0 => 1,
+                thousandths => thousandths,
+            };
+            match thousandths {
+                1000 => {}
+                0 => value.push_str(";q=0"),
+                _ => {
+                    let digits = alloc::format!("{:03}", thousandths);
+                    value.push_str(";q=0.");
+                    value.push_str(digits.trim_end_matches('0'));
+                }
+            }
+        }
+
+        self.insert_header((crate::header::ACCEPT.clone(), value.into()))
+    }
+
+    /// Creates a request without a body, see [`RequestBuilder::build_with_body`]
+    pub fn build(self) -> Result<Request<'a, (), S>> {
+        self.build_with_body(())
+    }
+
+    /// Creates the request, or returns the first error raised while building it.
+    /// Also rejects header values that would corrupt the message and uris without a host,
+    /// so these show up here instead of when the request is written.
+    pub fn build_with_body<T>(self, body: T) -> Result<Request<'a, T, S>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        for (key, value) in self.headers.as_slice() {
+            check_header(key, value)?;
+        }
+        check_header(&crate::header::USER_AGENT, &self.user_agent)?;
+        // a uri without authority takes the host from the headers, see `Uri::parse_origin_form`
+        let host_header = || {
+            self.headers
+                .as_slice()
+                .iter()
+                .any(|(key, _)| key.inner.eq_ignore_ascii_case("host"))
+        };
+        if self.uri.host().is_empty() && !(self.uri.authority().is_empty() && host_header()) {
+            return Err(UriError::EmptyAuthority.into());
+        }
+
+        Ok(Request {
             header: Header {
                 method: self.method,
                 uri: self.uri,
                 headers: self.headers,
+                no_content_length_on_empty: self.no_content_length_on_empty,
+                asterisk_form: self.asterisk_form,
+                version: self.version,
+                user_agent: Some(self.user_agent),
             },
             body,
-        }
-    }
-
-    pub fn build(self) -> Request<'a, ()> {
-        self.body(())
+        })
     }
 }
 
@@ -426,154 +1479,1003 @@ impl<T> Request<'a, D> {
             write!(buf, "{}: {}\r\n", key, value)?;
         }
 
-        write!(buf, "User-Agent: {USER_AGENT}\r\n")?;
+        write!(buf, "User-Agent: {DEFAULT_USER_AGENT}\r\n")?;
+
+        Ok(())
+    }
+
+    pub fn build_header_no_body<W: Write>(&self, mut buf: W) -> Result<(), Error> {
+        self.build_header_no_body_inner(&mut buf)?;
+
+        write!(buf, "\r\n")?;
+        Ok(())
+    }
+}
+
+impl<'a, const D: usize> Request<'a, D> {
+    pub fn build<W: Write>(self, body: &'_ [u8], mut buf: W) -> Result<(), Error> {
+        self.build_header_no_body_inner(&mut buf)?;
+
+        write!(buf, "Content-Length: {}\r\n\r\n", body.len())?;
+        buf.write(body).map_err(|e| Error::from(e.kind()))?;
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(all(feature = "serde_json", feature = "alloc"))]
+impl<'a, const D: usize> Request<'a, D> {
+    pub fn build_json<W: Write, T: Serialize>(mut self, body: T, buf: W) -> Result<(), Error> {
+        self = self.set_json()?;
+        let body_ser = serde_json::to_string(&body)?;
+
+        self.build(body_ser.as_bytes(), buf)
+    }
+}
+ */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::from_utf8;
+
+    #[test]
+    fn build_no_body() {
+        let req = RequestBuilder::get("https://api.aqsense.no/v1/health")
+            .unwrap()
+            .body(())
+            .unwrap();
+
+        let buf = req.to_vec().unwrap();
+
+        println!("{}", from_utf8(buf.as_slice()).unwrap());
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut req = httparse::Request::new(&mut headers);
+
+        let body_status = req.parse(buf.as_slice()).unwrap();
+
+        // Check path, method and version
+        assert_eq!(req.path.unwrap(), "/v1/health");
+        assert_eq!(req.method.unwrap(), "GET");
+        assert_eq!(req.version.unwrap(), 1);
+
+        // check content type
+        assert!(!req
+            .headers
+            .iter()
+            .any(|header| header.name == http::header::CONTENT_TYPE));
+
+        // check validity of request
+        assert!(body_status.is_complete());
+
+        // check body
+        assert_eq!(buf[body_status.unwrap()..].len(), 0);
+    }
+
+    #[test]
+    fn build_str_body() {
+        let body = "hei";
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(body)
+            .unwrap();
+
+        let buf = req.to_vec().unwrap();
+
+        println!("{}", from_utf8(buf.as_slice()).unwrap());
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut req = httparse::Request::new(&mut headers);
+
+        let body_status = req.parse(buf.as_slice()).unwrap();
+
+        // Check path, method and version
+        assert_eq!(req.path.unwrap(), "/");
+        assert_eq!(req.method.unwrap(), "POST");
+        assert_eq!(req.version.unwrap(), 1);
+
+        // check content type
+        let ct = req
+            .headers
+            .iter()
+            .find(|header| header.name == http::header::CONTENT_TYPE)
+            .unwrap();
+        assert_eq!(ct.value, crate::mime::TEXT_PLAIN_UTF_8.as_ref());
+
+        // check validity of request
+        assert!(body_status.is_complete());
+
+        // check body
+        assert_eq!(&buf[body_status.unwrap()..], body.as_bytes());
+    }
+
+    #[test]
+    fn build_byte_body() {
+        let body = b"hei";
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(body.as_slice())
+            .unwrap();
+
+        let buf = req.to_vec().unwrap();
+
+        println!("{}", from_utf8(buf.as_slice()).unwrap());
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut req = httparse::Request::new(&mut headers);
+
+        let body_status = req.parse(buf.as_slice()).unwrap();
+
+        // Check path, method and version
+        assert_eq!(req.path.unwrap(), "/");
+        assert_eq!(req.method.unwrap(), "POST");
+        assert_eq!(req.version.unwrap(), 1);
+
+        // check content type
+        let ct = req
+            .headers
+            .iter()
+            .find(|header| header.name == http::header::CONTENT_TYPE)
+            .unwrap();
+        assert_eq!(ct.value, crate::mime::APPLICATION_OCTET_STREAM.as_ref());
+
+        // check validity of request
+        assert!(body_status.is_complete());
+
+        // check body
+        assert_eq!(&buf[body_status.unwrap()..], body);
+    }
+
+    #[test]
+    fn build_websocket_upgrade() {
+        let req = RequestBuilder::get("ws://test.com/chat")
+            .unwrap()
+            .websocket_upgrade("dGhlIHNhbXBsZSBub25jZQ==")
+            .build()
+            .unwrap();
+
+        let buf = req.to_vec().unwrap();
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut req = httparse::Request::new(&mut headers);
+        assert!(req.parse(buf.as_slice()).unwrap().is_complete());
+
+        let header = |name: http::HeaderName| {
+            req.headers
+                .iter()
+                .find(|header| header.name == name)
+                .unwrap()
+                .value
+        };
+
+        assert_eq!(header(http::header::UPGRADE), b"websocket");
+        assert_eq!(header(http::header::CONNECTION), b"Upgrade");
+        assert_eq!(header(http::header::SEC_WEBSOCKET_VERSION), b"13");
+        assert_eq!(
+            header(http::header::SEC_WEBSOCKET_KEY),
+            b"dGhlIHNhbXBsZSBub25jZQ=="
+        );
+    }
+
+    #[test]
+    fn display() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("hei")
+            .unwrap();
+
+        assert_eq!(
+            std::format!("{}", req),
+            from_utf8(&req.to_vec().unwrap()).unwrap()
+        );
+        assert_eq!(
+            std::format!("{}", req.header),
+            "POST / HTTP/1.1\r\nhost: google.com\r\nuser-agent: :)\r\n\r\n"
+        );
+
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body([0xffu8, 0xfe].as_slice())
+            .unwrap();
+
+        assert!(std::format!("{}", req).ends_with("content-length: 2\r\n\r\n<2 bytes>"));
+
+        let req = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .raw_header(b"x-bin", &[b'a', 0xff, 0xfe])
+            .build()
+            .unwrap();
+
+        assert!(std::format!("{}", req).contains("x-bin: a\u{fffd}\u{fffd}\r\n"));
+
+        // values the public fields allow, but writing rejects, are escaped instead of failing
+        let mut req = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+        req.header.headers.push(("x".into(), "ø\r\nb".into()));
+        assert!(std::format!("{}", req).ends_with("x: ø\\r\\nb\r\n\r\n"));
+        assert!(req.to_vec().is_err());
+
+        struct Failing;
+        impl ToRequestBody for Failing {
+            fn write_body<W: Write>(&self, _w: W) -> Result<()>
+            where
+                crate::error::Error: From<<W as ErrorType>::Error>,
+            {
+                Err(Error::FmtError)
+            }
+
+            fn content_type(&self) -> Option<HeaderValue<'_>> {
+                Some("text/plain".into())
+            }
+        }
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(Failing)
+            .unwrap();
+        assert!(std::format!("{}", req).ends_with("user-agent: :)\r\n\r\n<body error>"));
+    }
+
+    #[test]
+    fn expect_continue() {
+        let req = RequestBuilder::post("https://google.com/upload")
+            .unwrap()
+            .expect_continue()
+            .body("large body")
+            .unwrap();
+
+        let mut header = Vec::new();
+        req.write_header_only(&mut header).unwrap();
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut parsed = httparse::Request::new(&mut headers);
+        assert_eq!(
+            parsed.parse(&header).unwrap(),
+            httparse::Status::Complete(header.len())
+        );
+        let expect = parsed
+            .headers
+            .iter()
+            .find(|header| header.name == http::header::EXPECT)
+            .unwrap();
+        assert_eq!(expect.value, b"100-continue");
+
+        let mut body = Vec::new();
+        req.write_body_to(&mut body).unwrap();
+        assert_eq!(body, b"large body");
+
+        header.extend_from_slice(&body);
+        assert_eq!(header, req.to_vec().unwrap());
+    }
+
+    #[test]
+    fn bodyless_methods_omit_content_length() {
+        for method in [
+            Method::Options,
+            Method::Get,
+            Method::Post,
+            Method::Put,
+            Method::Delete,
+            Method::Head,
+            Method::Trace,
+            Method::Connect,
+            Method::Patch,
+        ] {
+            let req = RequestBuilder::with_storage(method, "http://test.com/", Vec::new())
+                .unwrap()
+                .build()
+                .unwrap();
+            let buf = req.to_vec().unwrap();
+            let buf = core::str::from_utf8(&buf).unwrap();
+
+            assert!(buf.starts_with(method.str()), "{}", buf);
+            assert!(
+                !buf.to_ascii_lowercase().contains("content-length"),
+                "{}",
+                buf
+            );
+            assert!(
+                !buf.to_ascii_lowercase().contains("content-type"),
+                "{}",
+                buf
+            );
+        }
+    }
+
+    #[test]
+    fn if_match() {
+        let req = RequestBuilder::put("http://test.com/config")
+            .unwrap()
+            .if_match("\"v42\"")
+            .build_with_body("{}")
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        let buf = core::str::from_utf8(&buf).unwrap();
+        assert!(buf.contains("\r\nif-match: \"v42\"\r\n"), "{}", buf);
+
+        let res = RequestBuilder::put("http://test.com/config")
+            .unwrap()
+            .if_match("\"v42\"\r\nhost: evil.com")
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderValue)));
+
+        let mut resp =
+            Response::new(b"HTTP/1.1 412 Precondition Failed\r\nContent-Length: 0\r\n\r\n");
+        assert!(resp.is_precondition_failed().unwrap());
+        assert!(!Response::new(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .is_precondition_failed()
+            .unwrap());
+    }
+
+    #[test]
+    fn origin_form_uri() {
+        let uri = Uri::parse_origin_form("/v1/health?verbose=1").unwrap();
+        let req = RequestBuilder::with_storage(Method::Get, uri, Vec::new())
+            .unwrap()
+            .insert_header((crate::header::HOST.clone(), "192.168.1.5:8080".into()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.to_vec().unwrap(),
+            b"GET /v1/health?verbose=1 HTTP/1.1\r\nuser-agent: :)\r\nhost: 192.168.1.5:8080\r\n\r\n"
+        );
+
+        let res = RequestBuilder::with_storage(
+            Method::Get,
+            Uri::parse_origin_form("/v1/health").unwrap(),
+            Vec::new(),
+        )
+        .unwrap()
+        .build();
+        assert!(matches!(
+            res,
+            Err(Error::InvalidUri(UriError::EmptyAuthority))
+        ));
+    }
+
+    #[test]
+    fn max_forwards() {
+        let req = RequestBuilder::with_storage(Method::Trace, "http://test.com/", Vec::new())
+            .unwrap()
+            .max_forwards(5)
+            .max_forwards(3)
+            .build()
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert_eq!(
+            buf,
+            b"TRACE / HTTP/1.1\r\nhost: test.com\r\nuser-agent: :)\r\nmax-forwards: 3\r\n\r\n"
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "TRACE requests must not have a body")]
+    fn trace_with_body() {
+        let req = RequestBuilder::with_storage(Method::Trace, "http://test.com/", Vec::new())
+            .unwrap()
+            .build_with_body("body")
+            .unwrap();
+        let _ = req.to_vec();
+    }
+
+    #[test]
+    fn no_content_length_on_empty() {
+        let empty: &[u8] = &[];
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .no_content_length_on_empty()
+            .build_with_body(empty)
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert_eq!(
+            buf,
+            b"GET / HTTP/1.1\r\nhost: test.com\r\nuser-agent: :)\r\n\r\n"
+        );
+
+        // a body is still described
+        let req = RequestBuilder::post("http://test.com/")
+            .unwrap()
+            .no_content_length_on_empty()
+            .build_with_body("a")
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(core::str::from_utf8(&buf)
+            .unwrap()
+            .contains("content-length: 1\r\n"));
+
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build_with_body(empty)
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(core::str::from_utf8(&buf)
+            .unwrap()
+            .contains("content-length: 0\r\n"));
+    }
+
+    #[test]
+    fn options_asterisk() {
+        let req = RequestBuilder::options_asterisk("test.com:8080")
+            .unwrap()
+            .build()
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut parsed = httparse::Request::new(&mut headers);
+        assert!(parsed.parse(&buf).unwrap().is_complete());
+        assert_eq!(parsed.method, Some("OPTIONS"));
+        assert_eq!(parsed.path, Some("*"));
+        assert_eq!(parsed.version, Some(1));
+        let host = parsed.headers.iter().find(|h| h.name == "host").unwrap();
+        assert_eq!(host.value, b"test.com:8080");
+    }
+
+    #[test]
+    fn http10() {
+        let req = RequestBuilder::get("http://test.com/path")
+            .unwrap()
+            .version(Version::Http10)
+            .build()
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(buf.starts_with(b"GET /path HTTP/1.0\r\n"));
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut parsed = httparse::Request::new(&mut headers);
+        assert!(parsed.parse(&buf).unwrap().is_complete());
+        assert_eq!(parsed.version, Some(0));
+    }
+
+    #[test]
+    fn connection() {
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .append_header(("Connection".into(), "upgrade".into()))
+            .connection_close()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.header.headers,
+            [(crate::header::CONNECTION.clone(), "close".into())]
+        );
+
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .connection_close()
+            .connection_keep_alive()
+            .build()
+            .unwrap();
+        assert!(req.header.headers.is_empty());
+
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .version(Version::Http10)
+            .connection_keep_alive()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.header.headers,
+            [(crate::header::CONNECTION.clone(), "keep-alive".into())]
+        );
+    }
+
+    #[test]
+    fn raw_header() {
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .append_header(("x-first".into(), "1".into()))
+            .raw_header(b"x-raw", b"\x01\xfe\t\xff")
+            .append_header(("x-last".into(), "2".into()))
+            .build()
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(buf.ends_with(b"x-first: 1\r\nx-raw: \x01\xfe\t\xff\r\nx-last: 2\r\n\r\n"));
+
+        let res = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .raw_header(b"x-raw", b"a\r\nhost: evil.com")
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderValue)));
+
+        let res = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .raw_header(b"x-\xff", b"a")
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderKey)));
+    }
+
+    #[test]
+    fn conditional_from() {
+        let mut prev = Response::new(
+            b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 30 Nov 2022 10:29:55 GMT\r\nContent-Length: 0\r\n\r\n",
+        );
+        let req = RequestBuilder::conditional_from("http://test.com/state", &mut prev)
+            .unwrap()
+            .build()
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(buf.ends_with(
+            b"if-none-match: \"v1\"\r\nif-modified-since: Wed, 30 Nov 2022 10:29:55 GMT\r\n\r\n"
+        ));
+
+        let mut prev = Response::new(b"HTTP/1.1 200 OK\r\nETag: \"v2\"\r\n\r\n");
+        let req = RequestBuilder::conditional_from("http://test.com/state", &mut prev)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.header.headers,
+            [(crate::header::IF_NONE_MATCH.clone(), "\"v2\"".into())]
+        );
 
-        Ok(())
+        let mut resp = Response::new(b"HTTP/1.1 304 Not Modified\r\nETag: \"v2\"\r\n\r\n");
+        assert!(resp.is_not_modified().unwrap());
     }
 
-    pub fn build_header_no_body<W: Write>(&self, mut buf: W) -> Result<(), Error> {
-        self.build_header_no_body_inner(&mut buf)?;
+    #[test]
+    fn reuse_header() {
+        let header: Header<'static> = RequestBuilder::post("http://test.com/reading")
+            .unwrap()
+            .insert_header(("x-token".into(), "abc".into()))
+            .build()
+            .unwrap()
+            .header
+            .into_owned();
 
-        write!(buf, "\r\n")?;
-        Ok(())
+        let first = header.clone_into_request("21.5");
+        let second = first.with_body("22.0");
+        assert_eq!(second.header, header);
+        assert!(second
+            .to_vec()
+            .unwrap()
+            .ends_with(b"x-token: abc\r\ncontent-type: text/plain; charset=utf-8\r\ncontent-length: 4\r\n\r\n22.0"));
     }
-}
 
-impl<'a, const D: usize> Request<'a, D> {
-    pub fn build<W: Write>(self, body: &'_ [u8], mut buf: W) -> Result<(), Error> {
-        self.build_header_no_body_inner(&mut buf)?;
-
-        write!(buf, "Content-Length: {}\r\n\r\n", body.len())?;
-        buf.write(body).map_err(|e| Error::from(e.kind()))?;
+    #[test]
+    fn header_capacity() {
+        let builder = RequestBuilder::with_capacity(Method::Get, "http://test.com/", 8).unwrap();
+        assert!(builder.headers.capacity() >= 8);
 
-        Ok(())
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .reserve(4)
+            .insert_header(("x-a".into(), "1".into()))
+            .build()
+            .unwrap();
+        assert!(req.header.headers.capacity() >= 4);
+        assert_eq!(req.header.headers.len(), 1);
     }
-}
-
 
+    #[test]
+    fn serialized_len() {
+        let req = RequestBuilder::post("http://test.com/path?q=1")
+            .unwrap()
+            .insert_header(("x-token".into(), "abc".into()))
+            .build_with_body("hei")
+            .unwrap();
+        assert_eq!(req.serialized_len().unwrap(), req.to_vec().unwrap().len());
 
-#[cfg(all(feature = "serde_json", feature = "alloc"))]
-impl<'a, const D: usize> Request<'a, D> {
-    pub fn build_json<W: Write, T: Serialize>(mut self, body: T, buf: W) -> Result<(), Error> {
-        self = self.set_json()?;
-        let body_ser = serde_json::to_string(&body)?;
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.serialized_len().unwrap(), req.to_vec().unwrap().len());
+
+        struct Unsized;
+        impl ToRequestBody for Unsized {
+            fn write_body<W: Write>(&self, mut w: W) -> Result<()>
+            where
+                crate::error::Error: From<<W as ErrorType>::Error>,
+            {
+                Ok(w.write_all(b"abc")?)
+            }
 
-        self.build(body_ser.as_bytes(), buf)
+            fn content_type(&self) -> Option<HeaderValue<'_>> {
+                Some("text/plain".into())
+            }
+        }
+        let req = RequestBuilder::post("http://test.com/")
+            .unwrap()
+            .body(Unsized)
+            .unwrap();
+        assert!(matches!(
+            req.serialized_len(),
+            Err(Error::UnknownContentLength)
+        ));
     }
-}
- */
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use core::str::from_utf8;
 
     #[test]
-    fn build_no_body() {
-        let req = RequestBuilder::get("https://api.aqsense.no/v1/health")
+    fn raw_body() {
+        let req = RequestBuilder::post("http://test.com/")
             .unwrap()
-            .body(());
+            .build_with_body(RawBody {
+                bytes: b"\x89PNG",
+                content_type: "image/png".into(),
+            })
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        assert!(buf.ends_with(b"content-type: image/png\r\ncontent-length: 4\r\n\r\n\x89PNG"));
+    }
 
+    #[test]
+    fn user_agent() {
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .user_agent("acme-sensor/1.2")
+            .insert_header((crate::header::USER_AGENT.clone(), "ignored".into()))
+            .build()
+            .unwrap();
         let buf = req.to_vec().unwrap();
+        assert_eq!(
+            buf,
+            b"GET / HTTP/1.1\r\nhost: test.com\r\nuser-agent: acme-sensor/1.2\r\n\r\n"
+        );
 
-        println!("{}", from_utf8(buf.as_slice()).unwrap());
+        let res = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .user_agent("a\r\nhost: evil.com")
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderValue)));
+    }
 
-        let mut headers = [httparse::EMPTY_HEADER; 16];
-        let mut req = httparse::Request::new(&mut headers);
+    #[test]
+    fn parse_request() {
+        let buf = b"\r\nPOST /api/v1/data?x=1 HTTP/1.1\r\nHost: sensor.local:8080\r\nUser-Agent: client/1.0\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        let (mut header, offset) = parse(buf).unwrap();
+        assert_eq!(header.method, Method::Post);
+        assert_eq!(
+            header.uri.as_str(),
+            "http://sensor.local:8080/api/v1/data?x=1"
+        );
+        assert_eq!(header.version, Version::Http11);
+        assert_eq!(header.user_agent, Some(HeaderValue::from("client/1.0")));
+        assert_eq!(header.headers.len(), 2);
+        assert_eq!(&buf[offset..], b"{}");
+
+        // forward to another host, the body is passed through as is and declares its own length
+        header.uri = Uri::parse("http://upstream.local/api/v1/data?x=1").unwrap();
+        let req = header.clone_into_request(RawBody {
+            bytes: &buf[offset..],
+            content_type: "application/json".into(),
+        });
+        assert_eq!(
+            req.to_vec().unwrap(),
+            b"POST /api/v1/data?x=1 HTTP/1.1\r\nhost: upstream.local\r\nuser-agent: client/1.0\r\nContent-Type: application/json\r\ncontent-length: 2\r\n\r\n{}"
+        );
 
-        let body_status = req.parse(buf.as_slice()).unwrap();
+        let (header, _) = parse(b"OPTIONS * HTTP/1.0\nHost: a.b\n\n").unwrap();
+        assert!(header.asterisk_form);
+        assert_eq!(header.version, Version::Http10);
+        let (header, _) = parse(b"GET http://a.b/c HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(header.uri.path(), "/c");
+        assert_eq!(header.user_agent, None);
+        assert_eq!(
+            std::format!("{}", header),
+            "GET /c HTTP/1.1\r\nhost: a.b\r\n\r\n"
+        );
 
-        // Check path, method and version
-        assert_eq!(req.path.unwrap(), "/v1/health");
-        assert_eq!(req.method.unwrap(), "GET");
-        assert_eq!(req.version.unwrap(), 1);
+        // the length of a new body replaces the framing of the parsed one
+        let (header, _) =
+            parse(b"POST / HTTP/1.1\r\nHost: a.b\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+        assert_eq!(
+            header.clone_into_request("abc").to_vec().unwrap(),
+            b"POST / HTTP/1.1\r\nhost: a.b\r\ncontent-type: text/plain; charset=utf-8\r\ncontent-length: 3\r\n\r\nabc"
+        );
+        assert!(matches!(
+            parse(b"POST / HTTP/1.1\r\nHost: a.b\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n"),
+            Err(Error::AmbiguousFraming)
+        ));
+
+        assert!(matches!(
+            parse(b"GET / HTTP/1.1\r\nHost: a.b\r\n"),
+            Err(Error::Incomplete)
+        ));
+        assert!(matches!(
+            parse(b"GET / HTTP/1.1\r\n\r\n"),
+            Err(Error::InvalidUri(_))
+        ));
+        assert!(matches!(
+            parse(b"GET /\r\n\r\n"),
+            Err(Error::InvalidRequestLine)
+        ));
+        assert!(matches!(
+            parse(b"GET / HTTP/2\r\n\r\n"),
+            Err(Error::UnsupportedVersion)
+        ));
+        assert!(matches!(
+            parse(b"BREW / HTTP/1.1\r\n\r\n"),
+            Err(Error::UnknownMethod)
+        ));
+        assert!(matches!(
+            parse(b"GET / HTTP/1.1\r\nHost: a.b\r\nBad Name: x\r\n\r\n"),
+            Err(Error::InvalidHeaderKey)
+        ));
+    }
 
-        // check content type
-        assert!(!req
-            .headers
-            .iter()
-            .any(|header| header.name == http::header::CONTENT_TYPE));
+    #[test]
+    fn content_type_for_file() {
+        for (filename, ct) in [
+            ("reading.csv", "text/csv"),
+            ("logo.PNG", "image/png"),
+            ("firmware", "application/octet-stream"),
+            ("archive.tar.zst", "application/octet-stream"),
+        ] {
+            let req = RequestBuilder::put("http://test.com/upload")
+                .unwrap()
+                .content_type_for_file(filename)
+                .build_with_body(RawBody {
+                    bytes: b"",
+                    content_type: "text/plain".into(),
+                })
+                .unwrap();
+            let buf = req.to_vec().unwrap();
+            let buf = core::str::from_utf8(&buf).unwrap();
+            assert!(
+                buf.contains(&std::format!("content-type: {ct}\r\n")),
+                "{}",
+                buf
+            );
+            assert_eq!(buf.matches("content-type").count(), 1, "{}", buf);
+        }
+    }
 
-        // check validity of request
-        assert!(body_status.is_complete());
+    #[test]
+    fn typed_headers() {
+        let date = chrono::DateTime::from_timestamp(784111777, 0).unwrap();
+        let req = RequestBuilder::get("http://test.com/")
+            .unwrap()
+            .insert_header((crate::header::ACCEPT.clone(), "text/html".into()))
+            .accept_json()
+            .content_type(crate::mime::TEXT_CSV)
+            .if_modified_since(date)
+            .build_with_body("a,b")
+            .unwrap();
+        let buf = req.to_vec().unwrap();
+        let buf = core::str::from_utf8(&buf).unwrap();
+        assert_eq!(buf.matches("content-type").count(), 1, "{}", buf);
 
-        // check body
-        assert_eq!(buf[body_status.unwrap()..].len(), 0);
+        assert_eq!(
+            req.header.headers,
+            [
+                (crate::header::ACCEPT.clone(), "application/json".into()),
+                (crate::header::CONTENT_TYPE.clone(), "text/csv".into()),
+                (
+                    crate::header::IF_MODIFIED_SINCE.clone(),
+                    "Sun, 06 Nov 1994 08:49:37 GMT".into()
+                ),
+            ]
+        );
     }
 
     #[test]
-    fn build_str_body() {
-        let body = "hei";
-        let req = RequestBuilder::post("https://google.com/")
+    fn date_header() {
+        let date = chrono::DateTime::from_timestamp(784111777, 0).unwrap();
+        let req = RequestBuilder::get("http://test.com/")
             .unwrap()
-            .body(body);
-
+            .date(date)
+            .build()
+            .unwrap();
         let buf = req.to_vec().unwrap();
+        assert!(buf.ends_with(b"date: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n"));
 
-        println!("{}", from_utf8(buf.as_slice()).unwrap());
+        // the format is understood by the response side too
+        let resp = [&b"HTTP/1.1 204 No Content\r\n"[..], &buf[buf.len() - 39..]].concat();
+        assert_eq!(crate::response::Response::new(&resp).date().unwrap(), date);
 
-        let mut headers = [httparse::EMPTY_HEADER; 16];
-        let mut req = httparse::Request::new(&mut headers);
+        let req = RequestBuilder::put("http://test.com/config")
+            .unwrap()
+            .if_unmodified_since(date)
+            .build()
+            .unwrap();
+        assert_eq!(
+            req.header.headers,
+            [(
+                crate::header::IF_UNMODIFIED_SINCE.clone(),
+                "Sun, 06 Nov 1994 08:49:37 GMT".into()
+            )]
+        );
+    }
 
-        let body_status = req.parse(buf.as_slice()).unwrap();
+    #[test]
+    fn build_validates() {
+        let res = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .insert_header(("x-token".into(), "a\r\nhost: evil.com".into()))
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderValue)));
 
-        // Check path, method and version
-        assert_eq!(req.path.unwrap(), "/");
-        assert_eq!(req.method.unwrap(), "POST");
-        assert_eq!(req.version.unwrap(), 1);
+        let res = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .insert_header((HeaderKey::from_unchecked("x token"), "a".into()))
+            .build_with_body("body");
+        assert!(matches!(res, Err(Error::InvalidHeaderKey)));
+
+        for uri in ["http://user@/path", "http://:8080/path"] {
+            assert!(matches!(
+                RequestBuilder::get(uri).unwrap().build(),
+                Err(Error::InvalidUri(UriError::EmptyAuthority))
+            ));
+        }
+    }
 
-        // check content type
-        let ct = req
-            .headers
-            .iter()
-            .find(|header| header.name == http::header::CONTENT_TYPE)
-            .unwrap();
-        assert_eq!(ct.value, crate::mime::TEXT_PLAIN_UTF_8.as_ref());
+    #[test]
+    fn reject_header_injection() {
+        // bypass the builder, which would reject the key already
+        let write = |name, value| {
+            let mut req = RequestBuilder::get("https://google.com/")
+                .unwrap()
+                .build()
+                .unwrap();
+            req.header
+                .headers
+                .push((HeaderKey::from(name), HeaderValue::from(value)));
+            req.to_vec()
+        };
 
-        // check validity of request
-        assert!(body_status.is_complete());
+        assert!(write("x-token", "a b\tc").is_ok());
+        assert!(matches!(
+            write("x-token", "a\r\nhost: evil.com"),
+            Err(Error::InvalidHeaderValue)
+        ));
+        assert!(matches!(
+            write("x-token", "a\0"),
+            Err(Error::InvalidHeaderValue)
+        ));
+        assert!(matches!(
+            write("x token", "a"),
+            Err(Error::InvalidHeaderKey)
+        ));
+        assert!(matches!(write("", "a"), Err(Error::InvalidHeaderKey)));
+
+        assert!(HeaderKey::checked("x-token").is_ok());
+        assert!(matches!(
+            HeaderKey::checked("x:token"),
+            Err(Error::InvalidHeaderKey)
+        ));
+        assert!(HeaderValue::checked(b"a, b").is_ok());
+        assert!(matches!(
+            HeaderValue::checked(b"a\nb"),
+            Err(Error::InvalidHeaderValue)
+        ));
+    }
 
-        // check body
-        assert_eq!(&buf[body_status.unwrap()..], body.as_bytes());
+    #[test]
+    fn reject_invalid_key_on_insert() {
+        let res = RequestBuilder::with_storage(Method::Get, "https://google.com/", Vec::new())
+            .unwrap()
+            .insert_header(("X Bad: Header".into(), "a".into()))
+            .append_header((crate::header::ACCEPT.clone(), "text/plain".into()))
+            .build_with_body(());
+        assert!(matches!(res, Err(Error::InvalidHeaderKey)));
+
+        let res = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .raw_header(b"x-\xff", b"a")
+            .body("hei");
+        assert!(matches!(res, Err(Error::InvalidHeaderKey)));
+
+        // tchar as defined by RFC 7230
+        for b in 0..=u8::MAX {
+            let key = [b];
+            let Ok(key) = core::str::from_utf8(&key) else {
+                continue;
+            };
+            let tchar = b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+            assert_eq!(HeaderKey::checked(key).is_ok(), tchar, "{:?}", key);
+        }
+
+        assert!(matches!(
+            "x-custom".parse::<HeaderKey>(),
+            Ok(key) if key == HeaderKey::from("x-custom")
+        ));
+        assert!(matches!(
+            "x custom".parse::<HeaderKey>(),
+            Err(Error::InvalidHeaderKey)
+        ));
+        assert!(!HeaderKey::from_unchecked("x custom").is_valid());
     }
 
     #[test]
-    fn build_byte_body() {
-        let body = b"hei";
-        let req = RequestBuilder::post("https://google.com/")
+    fn insert_replaces_header() {
+        let req = RequestBuilder::get("https://google.com/")
             .unwrap()
-            .body(body.as_slice());
+            .insert_header(("Authorization".into(), "Bearer a".into()))
+            .insert_header(("authorization".into(), "Bearer b".into()))
+            .append_header((crate::header::ACCEPT.clone(), "text/plain".into()))
+            .append_header((crate::header::ACCEPT.clone(), "text/html".into()))
+            .build()
+            .unwrap();
 
-        let buf = req.to_vec().unwrap();
+        assert_eq!(
+            req.header.headers,
+            [
+                ("authorization".into(), "Bearer b".into()),
+                (crate::header::ACCEPT.clone(), "text/plain".into()),
+                (crate::header::ACCEPT.clone(), "text/html".into()),
+            ]
+        );
+    }
 
-        println!("{}", from_utf8(buf.as_slice()).unwrap());
+    #[test]
+    fn accept_quality_values() {
+        let accept = |media_types| {
+            let req = RequestBuilder::get("https://google.com/")
+                .unwrap()
+                .accept(media_types)
+                .build()
+                .unwrap();
+            std::string::String::from_utf8(req.header.headers[0].1.inner.to_vec()).unwrap()
+        };
 
-        let mut headers = [httparse::EMPTY_HEADER; 16];
-        let mut req = httparse::Request::new(&mut headers);
+        assert_eq!(
+            accept(&[("application/json", Some(0.9)), ("text/plain", Some(0.5))]),
+            "application/json;q=0.9, text/plain;q=0.5"
+        );
+        assert_eq!(
+            accept(&[
+                ("application/cbor", None),
+                ("application/json", Some(1.0)),
+                ("text/*", Some(0.04)),
+                ("*/*", Some(0.25))
+            ]),
+            "application/cbor, application/json, text/*;q=0.04, */*;q=0.25"
+        );
+        // only a quality of 0 refuses a media type
+        assert_eq!(
+            accept(&[
+                ("a/b", Some(0.001)),
+                ("c/d", Some(0.0001)),
+                ("e/f", Some(0.0)),
+                ("g/h", Some(-1.0)),
+                ("i/j", Some(0.12345))
+            ]),
+            "a/b;q=0.001, c/d;q=0.001, e/f;q=0, g/h;q=0, i/j;q=0.123"
+        );
+        assert_eq!(accept(&[]), "");
 
-        let body_status = req.parse(buf.as_slice()).unwrap();
+        let res = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .accept(&[("a/b", Some(f32::NAN))])
+            .build();
+        assert!(matches!(res, Err(Error::InvalidHeaderValue)));
+    }
 
-        // Check path, method and version
-        assert_eq!(req.path.unwrap(), "/");
-        assert_eq!(req.method.unwrap(), "POST");
-        assert_eq!(req.version.unwrap(), 1);
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn build_heapless() {
+        use crate::header::HeaderMap;
+
+        let req =
+            RequestBuilder::with_storage(Method::Get, "https://google.com/", HeaderMap::<2>::new())
+                .unwrap()
+                .insert_header((crate::header::ACCEPT.clone(), "text/plain".into()))
+                .build_with_body(())
+                .unwrap();
 
-        // check content type
-        let ct = req
-            .headers
-            .iter()
-            .find(|header| header.name == http::header::CONTENT_TYPE)
-            .unwrap();
         assert_eq!(
-            ct.value,
-            crate::mime::APPLICATION_OCTET_STREAM.inner.as_ref()
+            req.to_vec().unwrap(),
+            RequestBuilder::get("https://google.com/")
+                .unwrap()
+                .insert_header((crate::header::ACCEPT.clone(), "text/plain".into()))
+                .build()
+                .unwrap()
+                .to_vec()
+                .unwrap()
         );
 
-        // check validity of request
-        assert!(body_status.is_complete());
+        let res =
+            RequestBuilder::with_storage(Method::Get, "https://google.com/", HeaderMap::<2>::new())
+                .unwrap()
+                .websocket_upgrade("dGhlIHNhbXBsZSBub25jZQ==")
+                .build_with_body(());
 
-        // check body
-        assert_eq!(&buf[body_status.unwrap()..], body);
+        assert!(matches!(res, Err(Error::TooManyHeaders(2))));
     }
 
     #[cfg(feature = "serde_json")]
@@ -582,7 +2484,8 @@ mod tests {
         let body = "hei";
         let req = RequestBuilder::post("https://google.com/")
             .unwrap()
-            .body(body);
+            .body(body)
+            .unwrap();
 
         let buf = req.to_json_vec().unwrap();
 
@@ -615,12 +2518,31 @@ mod tests {
         assert_eq!(recv_body, body);
     }
 
+    #[cfg(feature = "serde_json_core")]
+    #[test]
+    fn build_json_core_body() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("hei")
+            .unwrap();
+
+        let mut body_buf = [0u8; 8];
+        let mut buf = Vec::new();
+        req.write_json_core_to(&mut buf, &mut body_buf).unwrap();
+
+        assert!(buf.ends_with(b"content-length: 5\r\n\r\n\"hei\""));
+
+        let mut body_buf = [0u8; 4];
+        let res = req.write_json_core_to(&mut Vec::new(), &mut body_buf);
+        assert!(matches!(res, Err(Error::BufferTooSmall(4, 5))));
+    }
+
     #[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
     #[cfg_attr(
-        feature = "serde_json",
+        any(feature = "serde_json", feature = "cbor"),
         derive(serde_derive::Serialize, serde_derive::Deserialize)
     )]
-    #[repr(packed)]
+    #[repr(C, packed)]
     struct TestStruct {
         a: u32,
         b: u32,
@@ -665,12 +2587,55 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "strict")]
+    #[test]
+    fn content_length_mismatch() {
+        struct Short;
+
+        impl ToRequestBody for Short {
+            fn write_body<W: Write>(&self, mut w: W) -> Result<()>
+            where
+                crate::error::Error: From<<W as ErrorType>::Error>,
+            {
+                Ok(w.write_all(b"abc")?)
+            }
+
+            fn content_type(&self) -> Option<HeaderValue<'_>> {
+                Some("text/plain".into())
+            }
+
+            fn content_length(&self) -> Option<usize> {
+                Some(4)
+            }
+        }
+
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(Short)
+            .unwrap();
+
+        assert!(matches!(
+            req.to_vec(),
+            Err(Error::ContentLengthMismatch {
+                declared: 4,
+                actual: 3
+            })
+        ));
+        assert!(RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("abc")
+            .unwrap()
+            .to_vec()
+            .is_ok());
+    }
+
     #[test]
     fn build_custom() {
         let body = TestStruct { a: 1, b: 2 };
         let req = RequestBuilder::post("https://google.com/")
             .unwrap()
-            .body(&body);
+            .body(&body)
+            .unwrap();
 
         let buf = req.to_vec().unwrap();
 
@@ -708,13 +2673,88 @@ mod tests {
         assert_eq!(new_body, body);
     }
 
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        let body = TestStruct { a: 1, b: 2 };
+        let buf = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(&body)
+            .unwrap()
+            .to_cbor_vec()
+            .unwrap();
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut req = httparse::Request::new(&mut headers);
+        let body_start = req.parse(buf.as_slice()).unwrap().unwrap();
+
+        let ct = req
+            .headers
+            .iter()
+            .find(|header| header.name == http::header::CONTENT_TYPE)
+            .unwrap();
+        assert_eq!(ct.value, crate::mime::APPLICATION_CBOR.as_ref());
+
+        let resp = crate::response::ResponseBuilder::status(crate::status::StatusCode::OK)
+            .body(&buf[body_start..])
+            .to_vec()
+            .unwrap();
+        let new_body: TestStruct = crate::response::Response::new(&resp).cbor().unwrap();
+
+        assert_eq!(new_body, body);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn write_async() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .insert_header((crate::header::ACCEPT.clone(), "text/plain".into()))
+            .body("hei")
+            .unwrap();
+        let mut buf = Vec::new();
+        crate::block_on(req.write_to_async(&mut buf)).unwrap();
+        assert_eq!(buf, req.to_vec().unwrap());
+
+        // buffered, since the body isn't a single slice
+        let body = TestStruct { a: 1, b: 2 };
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(&body)
+            .unwrap();
+        let mut buf = Vec::new();
+        crate::block_on(req.write_to_async(&mut buf)).unwrap();
+        assert_eq!(buf, req.to_vec().unwrap());
+
+        let req = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut buf = Vec::new();
+        crate::block_on(req.write_to_async(&mut buf)).unwrap();
+        assert_eq!(buf, req.to_vec().unwrap());
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "async"))]
+    #[test]
+    fn write_json_async() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body(TestStruct { a: 1, b: 2 })
+            .unwrap();
+        let mut buf = Vec::new();
+        crate::block_on(req.write_json_to_async(&mut buf)).unwrap();
+        assert_eq!(buf, req.to_json_vec().unwrap());
+    }
+
     #[cfg(feature = "serde_json")]
     #[test]
     fn build_custom_json() {
         let body = TestStruct { a: 1, b: 2 };
         let req = RequestBuilder::post("https://google.com/")
             .unwrap()
-            .body(&body);
+            .body(&body)
+            .unwrap();
 
         let buf = req.to_json_vec().unwrap();
 
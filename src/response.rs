@@ -1,7 +1,20 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
 use chrono::{DateTime, Utc};
 use core::num::ParseIntError;
 use core::str::from_utf8;
 use core::str::{FromStr, Utf8Error};
+use core::write;
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::auth::AuthChallenge;
+use crate::chunked::{ChunkedDecoder, DecodeStatus};
+use crate::header::{HeaderKey, HeaderStorage, HeaderValue, HeaderVec};
+use crate::mime::Mime;
+use crate::request::{check_header, write_header_value, write_message, Method, ToRequestBody};
+use crate::status::StatusCode;
+use crate::uri::Uri;
+use crate::writer::SliceWriter;
 
 #[allow(unused_imports)]
 use crate::prelude::*;
@@ -14,6 +27,20 @@ pub enum ResponseError {
     Incomplete,
     Error,
     ParseError(chrono::ParseError),
+    /// A chunk size line isn't valid hex, or a chunk isn't terminated by CRLF
+    InvalidChunk,
+    /// The content type isn't the one expected for the body
+    UnexpectedContentType,
+    /// The status line isn't `HTTP/<major>.<minor> <code> <reason>`
+    InvalidStatusLine,
+    /// The content type doesn't start with `type/subtype`
+    InvalidContentType,
+    /// The transfer encoding of the body can't be decoded here
+    UnsupportedEncoding,
+    /// Both `Transfer-Encoding: chunked` and `Content-Length` are sent, so peers
+    /// could disagree on where the body ends
+    #[cfg(feature = "strict")]
+    AmbiguousLength,
 }
 
 #[cfg(feature = "defmt")]
@@ -60,6 +87,25 @@ impl defmt::Format for ResponseError {
                     defmt::write!(fmt, "ParseError({})", e.to_string());
                 }
             }
+            ResponseError::InvalidChunk => {
+                defmt::write!(fmt, "InvalidChunk");
+            }
+            ResponseError::UnexpectedContentType => {
+                defmt::write!(fmt, "UnexpectedContentType");
+            }
+            ResponseError::InvalidStatusLine => {
+                defmt::write!(fmt, "InvalidStatusLine");
+            }
+            ResponseError::InvalidContentType => {
+                defmt::write!(fmt, "InvalidContentType");
+            }
+            ResponseError::UnsupportedEncoding => {
+                defmt::write!(fmt, "UnsupportedEncoding");
+            }
+            #[cfg(feature = "strict")]
+            ResponseError::AmbiguousLength => {
+                defmt::write!(fmt, "AmbiguousLength");
+            }
         }
     }
 }
@@ -95,16 +141,131 @@ pub struct Response<'a> {
     inner: &'a [u8],
 
     /// used to lazy evaluate status code
-    status_code: Option<u16>,
+    status_code: Option<StatusCode>,
 
     /// used to lazy evaluate content_length
     content_length: Option<usize>,
 
-    /// used to lazy evaluate header_length
-    header_length: Option<usize>,
+    /// used to lazy evaluate header_length, resuming a scan of a growing buffer
+    header_scan: HeaderScan,
 
     /// used to lazy evaluate content_type
-    content_type: Option<Option<&'a str>>,
+    content_type: Option<Option<Mime<'a>>>,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Response<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        // parse a fresh copy, since formatting can't fill in the lazily evaluated fields
+        let mut resp = Response::new(self.inner);
+        defmt::write!(
+            fmt,
+            "Response {{ status_code: {}, content_length: {} }}",
+            resp.status_code().ok(),
+            resp.content_length().ok()
+        );
+    }
+}
+
+/// Finds the end of the header in a buffer that grows between calls,
+/// resuming where the previous call stopped instead of scanning from the start again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeaderScan {
+    /// bytes scanned without finding the end of the header
+    scanned: usize,
+    header_len: Option<usize>,
+}
+
+impl HeaderScan {
+    pub const fn new() -> Self {
+        Self {
+            scanned: 0,
+            header_len: None,
+        }
+    }
+
+    /// Length of the header including the empty line ending it and any empty lines before the
+    /// status line, or `Incomplete` if the end hasn't been received yet. `buf` must start with the bytes passed to the previous calls.
+    pub fn header_len(&mut self, buf: &[u8]) -> Result<usize> {
+        if let Some(hl) = self.header_len {
+            return Ok(hl);
+        }
+        const MARKER: &[u8] = b"\r\n\r\n";
+
+        // the marker may straddle the end of the previous scan
+        let start = self
+            .scanned
+            .saturating_sub(MARKER.len() - 1)
+            .max(preamble_len(buf))
+            .min(buf.len());
+        match buf[start..].windows(MARKER.len()).position(|w| w == MARKER) {
+            Some(pos) => {
+                let len = start + pos + MARKER.len();
+                self.header_len = Some(len);
+                Ok(len)
+            }
+            None => {
+                self.scanned = buf.len();
+                Err(ResponseError::Incomplete)
+            }
+        }
+    }
+
+    /// Number of bytes scanned so far without finding the end of the header
+    pub fn scanned(&self) -> usize {
+        self.scanned
+    }
+}
+
+/// Snapshot of everything parsed from a response, see [`Response::parse_all`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedResponse<'a> {
+    pub status_code: StatusCode,
+    /// `None` for chunked bodies, which don't declare their length
+    pub content_length: Option<usize>,
+    pub content_type: Option<Mime<'a>>,
+    pub header_len: usize,
+    /// The body, decoded if it was sent chunked
+    pub body: Cow<'a, [u8]>,
+}
+
+/// The `Content-Range` of a `206 Partial Content` or `416 Range Not Satisfiable` response,
+/// see [`Response::content_range`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    /// First byte of the range, `None` for the unsatisfied form `bytes */total`
+    pub start: Option<u64>,
+    /// Last byte of the range, inclusive, `None` for the unsatisfied form
+    pub end: Option<u64>,
+    /// Size of the whole resource, `None` if the server sent `*`
+    pub total: Option<u64>,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ContentRange {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "ContentRange {{ start: {}, end: {}, total: {} }}",
+            self.start,
+            self.end,
+            self.total
+        );
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ParsedResponse<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "ParsedResponse {{ status_code: {}, content_length: {}, content_type: {}, header_len: {} }}",
+            self.status_code,
+            self.content_length,
+            self.content_type,
+            self.header_len
+        );
+    }
 }
 
 impl<'a> Response<'a> {
@@ -113,7 +274,7 @@ impl<'a> Response<'a> {
             inner: content,
             status_code: None,
             content_length: None,
-            header_length: None,
+            header_scan: HeaderScan::new(),
             content_type: None,
         }
     }
@@ -124,62 +285,189 @@ impl<'a> Response<'a> {
     }
 
     pub fn check(mut self) -> Result<Self> {
-        if self.header_len()? + self.content_length()? == self.inner.len() {
+        if self.total_len()? == self.inner.len() {
             Ok(self)
         } else {
             Err(ResponseError::Incomplete)
         }
     }
 
-    /// Calculate header len
-    pub fn header_len(&mut self) -> Result<usize> {
-        if let Some(hl) = self.header_length {
-            return Ok(hl);
+    /// Creates a response continuing `scan`, made while `content` was being received
+    pub fn with_header_scan(content: &'a [u8], scan: HeaderScan) -> Self {
+        Self {
+            header_scan: scan,
+            ..Self::new(content)
         }
-        const MARKER: &str = "\r\n\r\n";
+    }
 
-        if self.inner.len() < MARKER.len() {
-            return Err(ResponseError::Incomplete);
-        }
+    /// Parses the status code, content length, content type, header length and body at once,
+    /// returning them in a snapshot that can be used without `&mut` access
+    pub fn parse_all(&mut self) -> Result<ParsedResponse<'a>> {
+        let content_length = match self.is_chunked()? {
+            true => None,
+            false => Some(self.content_length()?),
+        };
 
-        for len in MARKER.len()..=self.inner.len() {
-            let slice = from_utf8(&self.inner[len - MARKER.len()..len])?;
-            if slice == MARKER {
-                self.header_length = Some(len);
-                return Ok(len);
+        Ok(ParsedResponse {
+            status_code: self.status_code()?,
+            content_length,
+            content_type: self.content_type()?,
+            header_len: self.header_len()?,
+            body: self.body_ref()?,
+        })
+    }
+
+    /// Parses the first complete response in `buf`, and returns it together with the remaining bytes.
+    /// Used to walk a buffer containing several pipelined responses back to back.
+    pub fn split_first(buf: &'a [u8]) -> Result<(Self, &'a [u8])> {
+        let len = Self::new(buf).total_len()?;
+        let (first, rest) = buf.split_at(len);
+        Ok((Self::new(first), rest))
+    }
+
+    /// Reads a response from `reader` into `buf`, until the header and the body are complete.
+    /// Bodies without content length or chunked encoding are read until the reader is exhausted.
+    /// Interim responses are skipped, see [`Response::is_interim`].
+    ///
+    /// Once the header gives the content length no more than the response is read, but reads
+    /// before that and reads of chunked bodies may go past its end. Those bytes, like the start
+    /// of the next response on the connection, are returned together with the response.
+    ///
+    /// Returns `Error::BufferTooSmall` if the response doesn't fit in `buf`.
+    pub fn read_from<R: Read>(mut reader: R, buf: &'a mut [u8]) -> crate::Result<(Self, &'a [u8])>
+    where
+        crate::Error: From<R::Error>,
+    {
+        let mut state = ReadState::new();
+        let len = loop {
+            let range = match state.next_step(buf)? {
+                ReadStep::Read(range) => range,
+                ReadStep::Done(len) => break len,
+            };
+            let n = reader.read(&mut buf[range])?;
+            if let Some(len) = state.advance(buf, n)? {
+                break len;
             }
-        }
+        };
 
-        Err(ResponseError::Incomplete)
+        Ok(state.finish(buf, len))
     }
 
-    /// Find the first line which contains the marker in the header, and returns the remainding string
-    /// This function is case insensitive on the marker
-    fn find_header_value<'b>(&mut self, marker: &'b str) -> Result<&'a str> {
-        for line in self.header()?.lines() {
-            if line.len() < marker.len() {
-                continue;
+    /// Async version of [`Response::read_from`], reading from an `embedded_io_async` reader
+    #[cfg(feature = "async")]
+    pub async fn read_from_async<R: embedded_io_async::Read>(
+        mut reader: R,
+        buf: &'a mut [u8],
+    ) -> crate::Result<(Self, &'a [u8])>
+    where
+        crate::Error: From<R::Error>,
+    {
+        let mut state = ReadState::new();
+        let len = loop {
+            let range = match state.next_step(buf)? {
+                ReadStep::Read(range) => range,
+                ReadStep::Done(len) => break len,
+            };
+            let n = reader.read(&mut buf[range]).await?;
+            if let Some(len) = state.advance(buf, n)? {
+                break len;
             }
-            if line
-                .chars()
-                .zip(marker.chars())
-                .all(|(a, b)| a.to_ascii_lowercase() == b.to_ascii_lowercase())
-            {
-                return Ok(&line[marker.len()..line.len()]);
+        };
+
+        Ok(state.finish(buf, len))
+    }
+
+    /// Number of bytes occupied by the response, header and (possibly chunked) body included.
+    /// This is where the next response starts when several share a buffer, see [`Response::split_first`].
+    pub fn total_len(&mut self) -> Result<usize> {
+        let header_len = self.header_len()?;
+
+        if self.is_chunked()? {
+            Ok(header_len + chunked_len(&self.inner[header_len..])?)
+        } else {
+            match header_len.checked_add(self.content_length()?) {
+                Some(len) if len <= self.inner.len() => Ok(len),
+                _ => Err(ResponseError::Incomplete),
+            }
+        }
+    }
+
+    /// Calculate header len, which is where the body starts.
+    /// Includes empty lines sent before the status line, which are otherwise ignored.
+    pub fn header_len(&mut self) -> Result<usize> {
+        self.header_scan.header_len(self.inner)
+    }
+
+    /// The header fields as name and value, parsed by the `httparse` backend if enabled.
+    /// Values include the continuation lines of obsolete line folding.
+    fn fields(&mut self) -> Result<Fields<'a>> {
+        Fields::parse(self.header()?)
+    }
+
+    /// Find the first header with the given name and returns the first line of its value,
+    /// without surrounding whitespace.
+    /// This function is case insensitive on the name
+    fn find_header_value(&mut self, name: &str) -> Result<&'a str> {
+        self.fields()?
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| {
+                let first = value.split('\n').next().unwrap_or_default();
+                first.trim_end_matches(['\r', ' ', '\t'])
+            })
+            .ok_or(ResponseError::HeaderNotFound)
+    }
+
+    /// Look up the value of the header `name` (case insensitive), without surrounding whitespace.
+    /// Values continued on the following lines using obsolete line folding are unfolded,
+    /// replacing each line break and its leading whitespace by a single space.
+    /// Only allocates when the value is actually folded.
+    pub fn header_value(&mut self, name: &str) -> Result<Cow<'a, str>> {
+        let value = self
+            .fields()?
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .ok_or(ResponseError::HeaderNotFound)?;
+
+        if !value.contains('\n') {
+            return Ok(Cow::Borrowed(value));
+        }
+
+        let mut unfolded = alloc::string::String::new();
+        for line in value.split('\n') {
+            if !unfolded.is_empty() {
+                unfolded.push(' ');
             }
+            unfolded.push_str(line.trim_matches(['\r', ' ', '\t']));
         }
+        Ok(Cow::Owned(unfolded))
+    }
 
-        Err(ResponseError::HeaderNotFound)
+    /// Iterate over the header fields as name and value, without surrounding whitespace.
+    /// Values include the line breaks of obsolete line folding, see [`Response::header_value`].
+    /// Fails if the header isn't valid utf8, see [`Response::headers_bytes`] for such headers.
+    pub fn headers(&mut self) -> Result<impl Iterator<Item = (&'a str, &'a str)>> {
+        self.fields()
+    }
+
+    /// Iterate over the header fields as raw name and value bytes, like [`Response::headers`]
+    /// but without requiring utf8, e.g. to forward the headers of a response verbatim.
+    /// The other header accessors still fail with `ResponseError::Utf8Error` on such a header.
+    pub fn headers_bytes(&mut self) -> Result<impl Iterator<Item = (&'a [u8], &'a [u8])>> {
+        #[cfg(feature = "httparse")]
+        let fields = ParsedFields::parse(self.header_bytes()?)?;
+        #[cfg(not(feature = "httparse"))]
+        let fields = RawFields::new(self.header_bytes()?);
+        Ok(fields)
     }
 
     /// Extract content type from header
-    pub fn content_type(&mut self) -> Result<Option<&str>> {
+    pub fn content_type(&mut self) -> Result<Option<Mime<'a>>> {
         if let Some(sc) = self.content_type {
             return Ok(sc);
         }
 
-        let ct = match self.find_header_value("content-type: ") {
-            Ok(v) => Some(v),
+        let ct = match self.find_header_value("content-type") {
+            Ok(v) => Some(Mime::parse(v).ok_or(ResponseError::InvalidContentType)?),
             Err(ResponseError::HeaderNotFound) => None,
             Err(e) => return Err(e),
         };
@@ -188,41 +476,308 @@ impl<'a> Response<'a> {
         Ok(ct)
     }
 
+    /// The raw value of the content type header, without surrounding whitespace,
+    /// for forwarding it verbatim instead of parsing it like [`Response::content_type`]
+    pub fn content_type_bytes(&mut self) -> Result<Option<&'a [u8]>> {
+        match self.find_header_value("content-type") {
+            Ok(v) => Ok(Some(v.as_bytes())),
+            Err(ResponseError::HeaderNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns true if the content type is `application/json` or a `+json` suffixed type
+    pub fn is_json(&mut self) -> Result<bool> {
+        Ok(self.content_type()?.is_some_and(|ct| {
+            let subtype = ct.subtype().as_bytes();
+            ct.essence().eq_ignore_ascii_case("application/json")
+                || subtype.len() >= 5 && subtype[subtype.len() - 5..].eq_ignore_ascii_case(b"+json")
+        }))
+    }
+
+    #[cfg(any(feature = "serde_json", feature = "serde_json_core"))]
+    fn check_json(&mut self, check_content_type: bool) -> Result<()> {
+        if check_content_type && !self.is_json()? {
+            return Err(ResponseError::UnexpectedContentType);
+        }
+        Ok(())
+    }
+
+    /// Deserialize the body as JSON, decoding it first if it is sent chunked.
+    /// With `check_content_type` the content type must be JSON, see [`Response::is_json`].
+    #[cfg(feature = "serde_json")]
+    pub fn json<T: serde::de::DeserializeOwned>(
+        &mut self,
+        check_content_type: bool,
+    ) -> crate::Result<T> {
+        self.check_json(check_content_type)?;
+        Ok(serde_json::from_slice(&self.body_ref()?)?)
+    }
+
+    /// Deserialize the body as JSON without allocating, borrowing strings from the buffer.
+    /// The body must be sent with a content length, chunked bodies are not decoded.
+    /// With `check_content_type` the content type must be JSON, see [`Response::is_json`].
+    #[cfg(feature = "serde_json_core")]
+    pub fn json_core<T: serde::Deserialize<'a>>(
+        &mut self,
+        check_content_type: bool,
+    ) -> crate::Result<T> {
+        self.check_json(check_content_type)?;
+        let (value, _) = serde_json_core::from_slice(self.body()?)?;
+        Ok(value)
+    }
+
+    /// Deserialize the body as CBOR, decoding it first if it is sent chunked
+    #[cfg(feature = "cbor")]
+    pub fn cbor<T: serde::de::DeserializeOwned>(&mut self) -> crate::Result<T> {
+        Ok(ciborium::from_reader(self.body_ref()?.as_ref())?)
+    }
+
+    /// Returns true if the body is sent with `Transfer-Encoding: chunked`.
+    /// A chunked body takes precedence over a `Content-Length` sent alongside it,
+    /// which is ignored, or rejected with `ResponseError::AmbiguousLength` with the `strict` feature.
+    pub fn is_chunked(&mut self) -> Result<bool> {
+        let chunked = match self.find_header_value("transfer-encoding") {
+            Ok(te) => te
+                .rsplit(',')
+                .next()
+                .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked")),
+            Err(ResponseError::HeaderNotFound) => false,
+            Err(e) => return Err(e),
+        };
+
+        #[cfg(feature = "strict")]
+        if chunked && self.declared_content_length()?.is_some() {
+            return Err(ResponseError::AmbiguousLength);
+        }
+
+        Ok(chunked)
+    }
+
+    /// Returns true if the server closes the connection after this response,
+    /// either with `Connection: close` or by answering with HTTP/1.0 without `keep-alive`
+    pub fn is_connection_close(&mut self) -> Result<bool> {
+        let connection = match self.find_header_value("connection") {
+            Ok(v) => v,
+            Err(ResponseError::HeaderNotFound) => "",
+            Err(e) => return Err(e),
+        };
+        let has_option = |option: &str| {
+            connection
+                .split(',')
+                .any(|o| o.trim().eq_ignore_ascii_case(option))
+        };
+
+        if has_option("close") {
+            return Ok(true);
+        }
+
+        let (status_line, _) = line(self.header_bytes()?, 0)?;
+        let (major, minor, _, _) = parse_status_line(status_line)?;
+        Ok((major, minor) == (1, 0) && !has_option("keep-alive"))
+    }
+
+    /// Parse the challenge of the `WWW-Authenticate` header, sent with `401 Unauthorized`.
+    /// Returns `None` if the header is absent.
+    pub fn www_authenticate(&mut self) -> Result<Option<AuthChallenge<'a>>> {
+        match self.find_header_value("www-authenticate") {
+            Ok(v) => Ok(Some(AuthChallenge::parse(v).ok_or(ResponseError::Error)?)),
+            Err(ResponseError::HeaderNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parse the `Content-Range` header, like `bytes 0-499/1234`, or `bytes */1234` sent with
+    /// `416 Range Not Satisfiable` to tell the size of the resource. Returns `None` if the header
+    /// is absent, and `ResponseError::Error` for units other than bytes or a malformed range.
+    pub fn content_range(&mut self) -> Result<Option<ContentRange>> {
+        let value = match self.find_header_value("content-range") {
+            Ok(v) => v,
+            Err(ResponseError::HeaderNotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let (unit, range) = value.split_once(' ').ok_or(ResponseError::Error)?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return Err(ResponseError::Error);
+        }
+        let (range, total) = range
+            .trim_start()
+            .split_once('/')
+            .ok_or(ResponseError::Error)?;
+        let total = match total {
+            "*" => None,
+            total => Some(u64::from_str(total)?),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            // the unsatisfied form needs the total
+            None if range == "*" && total.is_some() => (None, None),
+            Some((start, end)) => {
+                let (start, end) = (u64::from_str(start)?, u64::from_str(end)?);
+                if end < start || total.is_some_and(|total| end >= total) {
+                    return Err(ResponseError::Error);
+                }
+                (Some(start), Some(end))
+            }
+            None => return Err(ResponseError::Error),
+        };
+
+        Ok(Some(ContentRange { start, end, total }))
+    }
+
+    /// The target of a redirect, resolving the `Location` header against `base`, the uri of the request.
+    /// Relative references like `/path` and protocol-relative ones like `//cdn.example.com/file`
+    /// take the missing parts, such as the scheme, from `base`. Returns `None` if the header is absent.
+    pub fn location(&mut self, base: &Uri<'_>) -> crate::Result<Option<Uri<'static>>> {
+        match self.find_header_value("location") {
+            Ok(v) => Ok(Some(base.join(v)?)),
+            Err(ResponseError::HeaderNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The methods listed in the `Allow` header, sent with `405 Method Not Allowed` and in
+    /// answer to `OPTIONS`. Unknown methods are skipped, a missing header yields no methods.
+    pub fn allowed_methods(&mut self) -> Result<impl Iterator<Item = Method> + 'a> {
+        let allow = match self.find_header_value("allow") {
+            Ok(allow) => allow,
+            Err(ResponseError::HeaderNotFound) => "",
+            Err(e) => return Err(e),
+        };
+
+        Ok(allow
+            .split(',')
+            .filter_map(|method| Method::from_str(method.trim_matches([' ', '\t'])).ok()))
+    }
+
+    /// Returns true if this is an interim `1xx` response, like `100 Continue` or `103 Early Hints`,
+    /// which is followed by the final response. `101 Switching Protocols` is final.
+    pub fn is_interim(&mut self) -> Result<bool> {
+        Ok(matches!(self.status_code()?.as_u16(), 100 | 102..=199))
+    }
+
+    /// Returns true if this is an interim `100 Continue`, telling the client to send the request body
+    pub fn is_continue(&mut self) -> Result<bool> {
+        Ok(self.status_code()? == 100)
+    }
+
+    /// Returns true if a conditional request was answered with `304 Not Modified`,
+    /// meaning the previously received body is still current
+    pub fn is_not_modified(&mut self) -> Result<bool> {
+        Ok(self.status_code()? == 304)
+    }
+
+    /// Returns true if a conditional request like one sent with `If-Match` was answered with
+    /// `412 Precondition Failed`, meaning the resource changed and the request wasn't applied
+    pub fn is_precondition_failed(&mut self) -> Result<bool> {
+        Ok(self.status_code()? == 412)
+    }
+
+    /// Returns true if the server accepted a protocol upgrade (101 Switching Protocols)
+    pub fn is_switching_protocols(&mut self) -> Result<bool> {
+        Ok(self.status_code()? == 101)
+    }
+
+    /// Extract the `Sec-WebSocket-Accept` value from a WebSocket handshake response
+    pub fn sec_websocket_accept(&mut self) -> Result<&'a str> {
+        self.find_header_value("sec-websocket-accept")
+    }
+
     /// Extract the status code from the response
-    /// returns None if no status code is found
-    pub fn status_code(&mut self) -> Result<u16> {
+    /// returns `InvalidStatusLine` if the status line is malformed or the code is below 100
+    pub fn status_code(&mut self) -> Result<StatusCode> {
         if let Some(sc) = self.status_code {
             return Ok(sc);
         }
 
-        let sc = self.find_header_value("HTTP/1.1 ")?;
-        let status_code = u16::from_str(&sc[..3])?;
+        let header = self.header_bytes()?;
+        let (status_line, _) = line(header, 0)?;
+        let (_, _, status_code, _) = parse_status_line(status_line)?;
+        let status_code =
+            StatusCode::from_u16(status_code).ok_or(ResponseError::InvalidStatusLine)?;
         self.status_code = Some(status_code);
         Ok(status_code)
     }
 
     /// Extract the content length from the response
     /// returns None if no content length is found
+    /// returns `UnsupportedEncoding` for chunked bodies, whose `Content-Length` is ignored
     pub fn content_length(&mut self) -> Result<usize> {
         if let Some(cl) = self.content_length {
             return Ok(cl);
         }
-
-        if self.status_code()? == 204 {
-            self.content_length = Some(0);
-            return Ok(0);
+        if self.is_chunked()? {
+            return Err(ResponseError::UnsupportedEncoding);
         }
 
-        let cl = self.find_header_value("content-length: ")?;
-        let cl = usize::from_str(cl)?;
+        // A present content-length is honored without looking at the status line,
+        // only a missing one needs the status code to tell whether a body is expected
+        let cl = match self.declared_content_length()? {
+            Some(cl) => cl,
+            // interim responses, 204 and 304 never have a body
+            None if matches!(self.status_code()?.as_u16(), 100..=199 | 204 | 304) => 0,
+            None => return Err(ResponseError::HeaderNotFound),
+        };
         self.content_length = Some(cl);
         Ok(cl)
     }
 
+    /// Length of the body of the response to a `method` request. Unlike [`Response::content_length`]
+    /// this ignores the headers of responses that never have a body, see [`Response::total_len_for`].
+    pub fn content_length_for(&mut self, method: Method) -> Result<usize> {
+        if self.is_bodiless(method)? {
+            return Ok(0);
+        }
+        self.content_length()
+    }
+
+    /// Like [`Response::total_len`] for the response to a `method` request. The responses to `HEAD`
+    /// requests, interim responses, `204 No Content` and `304 Not Modified` end with the header,
+    /// whatever their `Content-Length` or `Transfer-Encoding` say.
+    pub fn total_len_for(&mut self, method: Method) -> Result<usize> {
+        if self.is_bodiless(method)? {
+            return self.header_len();
+        }
+        self.total_len()
+    }
+
+    /// Returns true if the response to a `method` request has no body, see [`Response::total_len_for`]
+    fn is_bodiless(&mut self, method: Method) -> Result<bool> {
+        Ok(method == Method::Head || matches!(self.status_code()?.as_u16(), 100..=199 | 204 | 304))
+    }
+
+    /// The size of the resource as declared by `Content-Length`, or `None` if it isn't sent.
+    /// Unlike [`Response::content_length`] this is also meaningful for the response to a `HEAD`
+    /// request, whose body is empty, so it can be used to check a download fits before issuing a `GET`.
+    pub fn entity_length(&mut self) -> Result<Option<usize>> {
+        self.declared_content_length()
+    }
+
+    /// Parses every `Content-Length` header, tolerating surrounding whitespace and
+    /// repeated values. Differing values fail with `ResponseError::Error`, since peers
+    /// could disagree on where the body ends.
+    fn declared_content_length(&mut self) -> Result<Option<usize>> {
+        let mut declared = None;
+        for (key, value) in self.fields()? {
+            if !key.eq_ignore_ascii_case("content-length") {
+                continue;
+            }
+            // a list like `36, 36` is sent by some proxies merging duplicates
+            for value in value.split(',') {
+                let cl = usize::from_str(value.trim_matches([' ', '\t']))?;
+                if declared.replace(cl).is_some_and(|prev| prev != cl) {
+                    return Err(ResponseError::Error);
+                }
+            }
+        }
+        Ok(declared)
+    }
+
     /// Extracts the date from the header and parses it as DateTime<Utc>
     pub fn date(&mut self) -> Result<DateTime<Utc>> {
         Ok(
-            chrono::DateTime::parse_from_rfc2822(self.find_header_value("date: ")?)?
+            chrono::DateTime::parse_from_rfc2822(self.find_header_value("date")?)?
                 .with_timezone(&Utc),
         )
     }
@@ -230,8 +785,76 @@ impl<'a> Response<'a> {
     /// Extract the body of the response
     /// returns None if no content length is found
     /// returns empty slice if content length is 0
+    /// returns `Incomplete` if the buffer holds less than the declared content length
+    /// returns `UnsupportedEncoding` for chunked bodies, see [`Response::body_ref`]
     pub fn body(&mut self) -> Result<&'a [u8]> {
-        Ok(&self.inner[self.header_len()?..self.header_len()? + self.content_length()?])
+        let header_len = self.header_len()?;
+        let end = header_len
+            .checked_add(self.content_length()?)
+            .ok_or(ResponseError::Incomplete)?;
+        self.inner
+            .get(header_len..end)
+            .ok_or(ResponseError::Incomplete)
+    }
+
+    /// Iterate over the body in windows of `chunk_size` bytes, the last one may be shorter.
+    /// Panics if `chunk_size` is 0.
+    pub fn body_chunks(&mut self, chunk_size: usize) -> Result<core::slice::Chunks<'a, u8>> {
+        Ok(self.body()?.chunks(chunk_size))
+    }
+
+    /// Extract the body of the response, decoding it if it is sent chunked.
+    /// Borrows from the buffer when the body is identity encoded or consists of a single chunk,
+    /// and only allocates when several chunks must be joined.
+    pub fn body_ref(&mut self) -> Result<Cow<'a, [u8]>> {
+        if !self.is_chunked()? {
+            return Ok(Cow::Borrowed(self.body()?));
+        }
+
+        // make sure the full body, including the terminating chunk, has been received
+        self.total_len()?;
+
+        let mut chunks = Chunks::new(&self.inner[self.header_len()?..]);
+        let first = chunks.next_chunk()?.unwrap_or_default();
+
+        match chunks.next_chunk()? {
+            None => Ok(Cow::Borrowed(first)),
+            Some(second) => {
+                let mut body = Vec::from(first);
+                body.extend_from_slice(second);
+                for chunk in chunks {
+                    body.extend_from_slice(chunk?);
+                }
+                Ok(Cow::Owned(body))
+            }
+        }
+    }
+
+    /// Iterate over the trailer headers following the terminating chunk of a chunked body,
+    /// trimmed like the header fields. Yields nothing if the body isn't chunked or has no trailers.
+    pub fn trailers(&mut self) -> Result<impl Iterator<Item = (&'a str, &'a str)>> {
+        let mut section = "";
+
+        if self.is_chunked()? {
+            let header_len = self.header_len()?;
+            // the section ends with an empty line, which is not part of the trailers
+            let end = self.total_len()? - 2;
+
+            let mut chunks = Chunks::new(&self.inner[header_len..]);
+            for chunk in chunks.by_ref() {
+                chunk?;
+            }
+
+            section = from_utf8(&self.inner[header_len + chunks.pos..end])?;
+        }
+
+        Ok(section.split_terminator("\r\n").filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some((
+                key.trim_end_matches([' ', '\t']),
+                value.trim_matches([' ', '\t']),
+            ))
+        }))
     }
 
     /// Extract the body of the response and parses as str
@@ -241,94 +864,942 @@ impl<'a> Response<'a> {
         Ok(from_utf8(self.body()?)?)
     }
 
+    /// The body as text if the content type is textual, that is `text/*`, JSON, XML or any type
+    /// with a `charset` parameter, and the body is valid utf8. Returns `None` otherwise,
+    /// unlike [`Response::body_as_str`] which decodes any body.
+    pub fn text(&mut self) -> Result<Option<&'a str>> {
+        let textual = self.is_json()?
+            || self.content_type()?.is_some_and(|ct| {
+                let subtype = ct.subtype().as_bytes();
+                ct.type_().eq_ignore_ascii_case("text")
+                    || ct.essence().eq_ignore_ascii_case("application/xml")
+                    || subtype.len() >= 4
+                        && subtype[subtype.len() - 4..].eq_ignore_ascii_case(b"+xml")
+                    || ct.param("charset").is_some()
+            });
+        if !textual {
+            return Ok(None);
+        }
+        Ok(from_utf8(self.body()?).ok())
+    }
+
     /// Extract the header of the response
     /// returns None if no content length is found or header is invalid utf8
     pub fn header(&mut self) -> Result<&'a str> {
         Ok(from_utf8(self.header_bytes()?)?)
     }
 
-    /// Extract the header of the response
-    /// returns None if no content length is found or header is invalid utf8
+    /// Extract the header of the response, which unlike [`Response::header`] may be invalid utf8
     pub fn header_bytes(&mut self) -> Result<&'a [u8]> {
-        Ok(self.inner[..self.header_len()?].as_ref())
+        Ok(self.inner[preamble_len(self.inner)..self.header_len()?].as_ref())
     }
 }
 
-#[cfg(feature = "unstable")]
-mod unstable {
-    use super::*;
-
-    impl core::error::Error for ResponseError {
-        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
-            match self {
-                ResponseError::Utf8Error(e) => Some(e),
-                ResponseError::ParseIntError(e) => Some(e),
-                _ => None,
-            }
-        }
-    }
+/// Reads the header of a response into a buffer, and lets the body be pulled from the reader
+/// piece by piece, so it doesn't have to fit in memory.
+///
+/// The body is delimited by its content length, or by the end of the input if there is none.
+/// Chunked bodies are decoded with a [`ChunkedDecoder`], using the part of `buf` after the
+/// header for the raw chunks.
+pub struct ResponseReader<'a, R> {
+    reader: R,
+    buf: &'a mut [u8],
+    header_len: usize,
+    /// position of the next body byte already read into `buf`
+    pos: usize,
+    filled: usize,
+    /// body bytes left, `None` if the body ends with the input or is chunked
+    remaining: Option<usize>,
+    chunked: Option<ChunkedDecoder>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::NaiveDateTime;
-
-    const SIMPLE_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\ndate: Wed, 28 Sep 2022 08:23:31 GMT\r\n\r\n";
-    const BODY_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 132\r\nvary: Origin, Access-Control-Request-Method, Access-Control-Request-Headers\r\ncontent-type: application/json\r\ndate: Wed, 28 Sep 2022 09:00:53 GMT\r\n\r\n{\"status_code\":200,\"canonical_reason\":\"OK\",\"data\":\"tap.it backend built with rustc version 1.63.0 at 2022-09-05\",\"description\":null}";
-    const BODY_RESPONSE_2: &[u8] = b"HTTP/1.1 200 OK\r\nDate: Tue, 16 Apr 2024 11:18:11 GMT\r\nContent-Length: 36\r\nConnection: keep-alive\r\nvary: Origin, Access-Control-Request-Method, Access-Control-Request-Headers\r\n\r\n0ab5df47-4d09-493f-afa5-72f15d8edbc9";
+impl<'a, R: Read> ResponseReader<'a, R>
+where
+    crate::Error: From<R::Error>,
+{
+    /// Reads until the header of the final response is complete, skipping interim responses.
+    /// Returns `Error::BufferTooSmall` if it doesn't fit in `buf`.
+    pub fn new(reader: R, buf: &'a mut [u8]) -> crate::Result<Self> {
+        Self::open(reader, buf, None)
+    }
 
-    const NO_CONTENT: &[u8] = b"HTTP/1.1 204 No Content\r\nconnection: close\r\ndate: Wed, 30 Nov 2022 10:29:55 GMT\r\n\r\n";
+    /// Like [`ResponseReader::new`] for the response to a `method` request, so the responses
+    /// without a body, like the response to a `HEAD` request, don't wait for one.
+    /// See [`Response::total_len_for`].
+    pub fn for_request(reader: R, buf: &'a mut [u8], method: Method) -> crate::Result<Self> {
+        Self::open(reader, buf, Some(method))
+    }
 
-    #[test]
-    fn deserialize_date() {
-        let mut resp = Response::new(SIMPLE_RESPONSE);
-        let mut resp2 = Response::new(BODY_RESPONSE_2);
+    fn open(mut reader: R, buf: &'a mut [u8], method: Option<Method>) -> crate::Result<Self> {
+        let mut filled = 0;
+        let mut scan = HeaderScan::new();
+
+        let header_len = loop {
+            match scan.header_len(&buf[..filled]) {
+                Ok(len) if Response::new(&buf[..len]).is_interim()? => {
+                    buf.copy_within(len..filled, 0);
+                    filled -= len;
+                    scan = HeaderScan::new();
+                    continue;
+                }
+                Ok(len) => break len,
+                Err(ResponseError::Incomplete) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if filled == buf.len() {
+                return Err(crate::Error::BufferTooSmall(buf.len(), buf.len() + 1));
+            }
 
-        let expected_date = NaiveDateTime::new(
-            chrono::NaiveDate::from_ymd_opt(2022, 9, 28).unwrap(),
-            chrono::NaiveTime::from_hms_opt(8, 23, 31).unwrap(),
-        )
-        .and_utc();
+            match reader.read(&mut buf[filled..])? {
+                0 => return Err(ResponseError::Incomplete.into()),
+                n => filled += n,
+            }
+        };
 
-        let date = resp.date().unwrap();
-        assert_eq!(date, expected_date);
+        let mut head = Response::new(&buf[..header_len]);
+        let (remaining, chunked) = match method {
+            Some(method) if head.is_bodiless(method)? => (Some(0), None),
+            _ if head.is_chunked()? => (None, Some(ChunkedDecoder::new())),
+            _ => match head.content_length() {
+                Ok(len) => (Some(len), None),
+                Err(ResponseError::HeaderNotFound) => (None, None),
+                Err(e) => return Err(e.into()),
+            },
+        };
 
-        let date = resp2.date().unwrap();
-        let expected_date = NaiveDateTime::new(
-            chrono::NaiveDate::from_ymd_opt(2024, 4, 16).unwrap(),
-            chrono::NaiveTime::from_hms_opt(11, 18, 11).unwrap(),
-        )
-        .and_utc();
-        assert_eq!(date, expected_date);
+        Ok(Self {
+            reader,
+            buf,
+            header_len,
+            pos: header_len,
+            filled,
+            remaining,
+            chunked,
+        })
     }
 
-    #[test]
-    fn deserialize_simple() {
-        let mut resp = Response::new(SIMPLE_RESPONSE);
-        assert_eq!(resp.status_code().unwrap(), 200);
+    /// The response without its body, for reading the status and headers
+    pub fn response(&self) -> Response<'_> {
+        Response::new(&self.buf[..self.header_len])
+    }
 
-        assert_eq!(resp.content_length().unwrap(), 0);
+    /// Reads the next part of the body into `out`, returning the number of bytes read.
+    /// Returns 0 once the body is complete.
+    pub fn next_body_bytes(&mut self, out: &mut [u8]) -> crate::Result<usize> {
+        if self.chunked.is_some() {
+            return self.next_chunked_bytes(out);
+        }
 
-        println!("header: {}", resp.header().unwrap());
-        println!("body: {}", from_utf8(resp.body().unwrap()).unwrap());
+        let limit = self.remaining.map_or(out.len(), |r| r.min(out.len()));
+        if limit == 0 {
+            return Ok(0);
+        }
 
-        assert_eq!(resp.content_type().unwrap(), None);
+        let num = if self.pos < self.filled {
+            // hand out what was read together with the header first
+            let num = limit.min(self.filled - self.pos);
+            out[..num].copy_from_slice(&self.buf[self.pos..self.pos + num]);
+            self.pos += num;
+            num
+        } else {
+            let num = self.reader.read(&mut out[..limit])?;
+            if num == 0 && self.remaining.is_some() {
+                return Err(ResponseError::Incomplete.into());
+            }
+            num
+        };
 
-        assert_eq!(resp.body().unwrap().len(), 0);
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= num;
+        }
+        Ok(num)
     }
 
-    #[test]
-    fn deserialize_body() {
-        let mut resp = Response::new(BODY_RESPONSE);
-        let header = resp.header().unwrap();
+    /// Decodes the next part of a chunked body into `out`. The decoded data is never longer
+    /// than its input, so at most `out.len()` bytes of input are decoded at once.
+    fn next_chunked_bytes(&mut self, out: &mut [u8]) -> crate::Result<usize> {
+        let Some(decoder) = &mut self.chunked else {
+            return Ok(0);
+        };
+
+        while !decoder.is_done() && !out.is_empty() {
+            if self.pos == self.filled {
+                // the raw chunks are read into the buffer after the header
+                let end = self.buf.len().min(self.header_len + out.len());
+                if end == self.header_len {
+                    return Err(crate::Error::BufferTooSmall(
+                        self.buf.len(),
+                        self.buf.len() + 1,
+                    ));
+                }
+                match self.reader.read(&mut self.buf[self.header_len..end])? {
+                    0 => return Err(ResponseError::Incomplete.into()),
+                    n => (self.pos, self.filled) = (self.header_len, self.header_len + n),
+                }
+            }
+
+            let end = self.filled.min(self.pos + out.len());
+            let (status, num) = decode_into(decoder, &self.buf[self.pos..end], out)?;
+            self.pos = match status {
+                DecodeStatus::NeedMore => end,
+                DecodeStatus::Done { consumed } => self.pos + consumed,
+            };
+            if num > 0 {
+                return Ok(num);
+            }
+        }
+        Ok(0)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Decodes `input` into `out`, which must be at least as long, returning the number of bytes
+/// written. Kept outside of [`ResponseReader`], whose bound on the reader error confuses the
+/// inference of the writer error.
+fn decode_into(
+    decoder: &mut ChunkedDecoder,
+    input: &[u8],
+    out: &mut [u8],
+) -> crate::Result<(DecodeStatus, usize)> {
+    let mut w = SliceWriter::new(out);
+    let status = decoder.decode(input, &mut w)?;
+    Ok((status, w.len()))
+}
+
+/// A response owning its bytes, e.g. as returned by [`crate::client::Client::send`],
+/// so it can be returned without the caller managing the buffer.
+///
+/// Only the offset of the body is stored next to the bytes, anything else is parsed
+/// from a [`Response`] borrowing them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedResponse {
+    inner: Vec<u8>,
+    header_scan: HeaderScan,
+}
+
+impl OwnedResponse {
+    pub fn new(inner: Vec<u8>) -> Self {
+        let mut header_scan = HeaderScan::new();
+        // scan the header once, so the responses borrowed by the accessors don't repeat it.
+        // The result is only cached: an incomplete header isn't an error yet, it's reported
+        // lazily as `ResponseError::Incomplete` by the accessors that need the header.
+        let _ = header_scan.header_len(&inner);
+        Self { inner, header_scan }
+    }
+
+    /// Borrows the bytes as a [`Response`] for parsing
+    pub fn response(&self) -> Response<'_> {
+        Response::with_header_scan(&self.inner, self.header_scan)
+    }
+
+    pub fn status_code(&self) -> Result<StatusCode> {
+        self.response().status_code()
+    }
+
+    pub fn header_value(&self, name: &str) -> Result<Cow<'_, str>> {
+        self.response().header_value(name)
+    }
+
+    pub fn body(&self) -> Result<&[u8]> {
+        self.response().body()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.inner
+    }
+}
+
+impl From<Vec<u8>> for OwnedResponse {
+    fn from(inner: Vec<u8>) -> Self {
+        Self::new(inner)
+    }
+}
+
+/// Builder for responses written by a server
+pub struct ResponseBuilder<'a, T = ()> {
+    status: StatusCode,
+    /// overrides the canonical reason phrase of the status
+    reason: Option<&'a str>,
+    headers: HeaderVec<'a>,
+    body: T,
+    /// first error while building, returned when writing
+    error: Option<crate::Error>,
+}
+
+impl<'a> ResponseBuilder<'a> {
+    pub fn status(status: StatusCode) -> Self {
+        Self {
+            status,
+            reason: None,
+            headers: Vec::new(),
+            body: (),
+            error: None,
+        }
+    }
+
+    /// Starts a stream of server-sent events. Only the header is written, the events
+    /// follow as [`crate::sse::SseEvent`]s until the connection is closed.
+    pub fn sse() -> Self {
+        Self::status(StatusCode::OK)
+            .insert_header((
+                crate::header::CONTENT_TYPE.clone(),
+                crate::mime::TEXT_EVENT_STREAM.into(),
+            ))
+            .insert_header((
+                crate::header::CACHE_CONTROL.clone(),
+                HeaderValue::from_static(b"no-cache"),
+            ))
+    }
+}
+
+impl<'a, T> ResponseBuilder<'a, T> {
+    /// Sets a header, replacing any header with the same name. If the key or value is invalid,
+    /// the error is returned by [`ResponseBuilder::write_to`].
+    pub fn insert_header(mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> Self {
+        let res = check_header(&header.0, &header.1)
+            .and_then(|()| HeaderStorage::insert(&mut self.headers, header));
+        self.record(res);
+        self
+    }
+
+    /// Appends a header, keeping any header with the same name. If the key or value is invalid,
+    /// the error is returned by [`ResponseBuilder::write_to`].
+    pub fn append_header(mut self, header: (HeaderKey<'a>, HeaderValue<'a>)) -> Self {
+        let res = check_header(&header.0, &header.1)
+            .and_then(|()| HeaderStorage::push(&mut self.headers, header));
+        self.record(res);
+        self
+    }
+
+    /// Keeps the first error for the fallible terminals
+    fn record(&mut self, res: crate::Result<()>) {
+        if let Err(e) = res {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    /// Sends `reason` instead of the canonical reason phrase of the status.
+    /// Writing fails with `Error::InvalidHeaderValue` if it contains CR or LF.
+    pub fn reason(mut self, reason: &'a str) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    pub fn body<B>(self, body: B) -> ResponseBuilder<'a, B> {
+        ResponseBuilder {
+            status: self.status,
+            reason: self.reason,
+            headers: self.headers,
+            body,
+            error: self.error,
+        }
+    }
+}
+
+impl<'a, T: ToRequestBody> ResponseBuilder<'a, T> {
+    pub fn write_to<W: Write>(&self, w: W) -> crate::Result<()>
+    where
+        crate::error::Error: From<<W as ErrorType>::Error>,
+    {
+        if let Some(e) = &self.error {
+            // only the validation of headers is recorded, whose errors hold no data
+            return Err(match e {
+                crate::Error::InvalidHeaderKey => crate::Error::InvalidHeaderKey,
+                _ => crate::Error::InvalidHeaderValue,
+            });
+        }
+
+        let reason = match self.reason {
+            Some(reason) if reason.contains(['\r', '\n']) => {
+                return Err(crate::Error::InvalidHeaderValue)
+            }
+            Some(reason) => reason,
+            None => self.status.reason().unwrap_or_default(),
+        };
+
+        write_message(w, &self.body, true, |w, extra_headers| {
+            write!(w, "HTTP/1.1 {} {}\r\n", self.status.as_u16(), reason)?;
+
+            for (name, value) in self.headers.iter() {
+                write_header_value(name, value, w)?;
+            }
+
+            for (name, value) in extra_headers {
+                write_header_value(name, value, w)?;
+            }
+
+            write!(w, "\r\n")?;
+            Ok(())
+        })
+    }
+
+    pub fn to_vec(&self) -> crate::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Parses a status line like `HTTP/1.1 200 OK`, with or without the trailing CRLF,
+/// into the major and minor version, the status code and the reason phrase.
+pub fn parse_status_line(line: &[u8]) -> Result<(u8, u8, u16, &str)> {
+    let line = line.strip_suffix(b"\r\n").unwrap_or(line);
+
+    let (version, rest) = match line {
+        [b'H', b'T', b'T', b'P', b'/', major, b'.', minor, b' ', rest @ ..]
+            if major.is_ascii_digit() && minor.is_ascii_digit() =>
+        {
+            ((major - b'0', minor - b'0'), rest)
+        }
+        _ => return Err(ResponseError::InvalidStatusLine),
+    };
+
+    let (code, reason) = match rest {
+        [a, b, c, reason @ ..] if [a, b, c].iter().all(|d| d.is_ascii_digit()) => {
+            let code = [a, b, c]
+                .iter()
+                .fold(0u16, |code, &&d| code * 10 + u16::from(d - b'0'));
+            (code, reason)
+        }
+        _ => return Err(ResponseError::InvalidStatusLine),
+    };
+
+    // the reason phrase may be empty, in which case the separating space is optional
+    let reason = match reason {
+        [] => reason,
+        [b' ', reason @ ..] => reason,
+        _ => return Err(ResponseError::InvalidStatusLine),
+    };
+
+    Ok((version.0, version.1, code, from_utf8(reason)?))
+}
+
+/// Returns the length of the response once `buf` holds all of it, or `None` if more must be read.
+/// At the end of the input, a body without framing ends with the input.
+/// Knowing the `method` of the request, responses without a body end with the header, see
+/// [`Response::total_len_for`].
+pub(crate) fn read_progress(
+    buf: &[u8],
+    eof: bool,
+    scan: &mut HeaderScan,
+    method: Option<Method>,
+) -> Result<Option<usize>> {
+    let mut resp = Response::with_header_scan(buf, *scan);
+    let len = match method {
+        Some(method) => resp.total_len_for(method),
+        None => resp.total_len(),
+    };
+    *scan = resp.header_scan;
+
+    match len {
+        Ok(len) => Ok(Some(len)),
+        // no content length, so the body is delimited by the end of the input
+        Err(ResponseError::HeaderNotFound) if eof => Ok(Some(buf.len())),
+        Err(ResponseError::Incomplete | ResponseError::HeaderNotFound) if !eof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reading a response into a buffer, shared by [`Response::read_from`] and
+/// [`Response::read_from_async`] so they only differ in the read call
+struct ReadState {
+    filled: usize,
+    scan: HeaderScan,
+}
+
+/// What to do next while reading a response, see [`ReadState::next_step`]
+enum ReadStep {
+    /// Read into this part of the buffer
+    Read(core::ops::Range<usize>),
+    /// The response is complete and this long
+    Done(usize),
+}
+
+impl ReadState {
+    fn new() -> Self {
+        Self {
+            filled: 0,
+            scan: HeaderScan::new(),
+        }
+    }
+
+    /// Decides if more must be read, skipping interim responses received so far.
+    /// Once the length of the response is known, reads stop at its end.
+    fn next_step(&mut self, buf: &mut [u8]) -> crate::Result<ReadStep> {
+        loop {
+            if let Some(len) = read_progress(&buf[..self.filled], false, &mut self.scan, None)? {
+                if !Response::new(&buf[..len]).is_interim()? {
+                    return Ok(ReadStep::Done(len));
+                }
+                buf.copy_within(len..self.filled, 0);
+                self.filled -= len;
+                self.scan = HeaderScan::new();
+                continue;
+            }
+            if self.filled == buf.len() {
+                return Err(crate::Error::BufferTooSmall(buf.len(), needed_len(buf)));
+            }
+
+            let end = known_len(&buf[..self.filled]).map_or(buf.len(), |len| len.min(buf.len()));
+            return Ok(ReadStep::Read(self.filled..end));
+        }
+    }
+
+    /// Accounts for `n` bytes read, returns the length of the response at the end of the input
+    fn advance(&mut self, buf: &[u8], n: usize) -> crate::Result<Option<usize>> {
+        if n == 0 {
+            let len = read_progress(&buf[..self.filled], true, &mut self.scan, None)?;
+            return Ok(Some(len.unwrap_or(self.filled)));
+        }
+        self.filled += n;
+        Ok(None)
+    }
+
+    /// The response of length `len`, and the bytes read past its end
+    fn finish<'a>(self, buf: &'a [u8], len: usize) -> (Response<'a>, &'a [u8]) {
+        (
+            Response::with_header_scan(&buf[..len], self.scan),
+            &buf[len..self.filled],
+        )
+    }
+}
+
+/// Size needed for the response partially read into `buf`, if it can be known, otherwise one more byte
+fn needed_len(buf: &[u8]) -> usize {
+    known_len(buf).unwrap_or(buf.len() + 1)
+}
+
+/// Length of the response partially read into `buf`, known once the header is complete and
+/// declares the content length of a body that isn't chunked
+fn known_len(buf: &[u8]) -> Option<usize> {
+    let mut resp = Response::new(buf);
+    match (resp.header_len(), resp.content_length()) {
+        (Ok(header_len), Ok(content_length)) if !resp.is_chunked().unwrap_or(true) => {
+            header_len.checked_add(content_length)
+        }
+        _ => None,
+    }
+}
+
+/// Number of bytes of empty lines, CRLF or bare LF, sent before the status line by some servers.
+/// These are skipped, other preambles like a byte order mark are not.
+fn preamble_len(buf: &[u8]) -> usize {
+    buf.iter()
+        .position(|b| !matches!(b, b'\r' | b'\n'))
+        .unwrap_or(buf.len())
+}
+
+/// The header fields following the status line, as name and value without surrounding whitespace.
+/// Lines without a colon are skipped.
+#[cfg(not(feature = "httparse"))]
+struct Fields<'a> {
+    header: &'a str,
+    pos: usize,
+}
+
+#[cfg(not(feature = "httparse"))]
+impl<'a> Fields<'a> {
+    fn parse(header: &'a str) -> Result<Self> {
+        let mut fields = Self { header, pos: 0 };
+        // skip the status line
+        fields.pos = fields.line_end(0).0;
+        Ok(fields)
+    }
+
+    /// End of the line starting at `pos` without the line break, and the start of the next line
+    fn line_end(&self, pos: usize) -> (usize, usize) {
+        let end = self.header[pos..]
+            .find('\n')
+            .map_or(self.header.len(), |idx| pos + idx);
+        let next = (end + 1).min(self.header.len());
+        let end = match self.header[..end].ends_with('\r') {
+            true => end - 1,
+            false => end,
+        };
+        (next, end)
+    }
+}
+
+#[cfg(not(feature = "httparse"))]
+impl<'a> Iterator for Fields<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.header.len() {
+            let start = self.pos;
+            let (next, mut end) = self.line_end(start);
+            self.pos = next;
+
+            let Some(colon) = self.header[start..end].find(':') else {
+                continue;
+            };
+            // obsolete line folding continues the value on lines starting with whitespace
+            while self.header[self.pos..].starts_with([' ', '\t']) {
+                (self.pos, end) = self.line_end(self.pos);
+            }
+
+            let name = self.header[start..start + colon].trim_end_matches([' ', '\t']);
+            let value = self.header[start + colon + 1..end].trim_matches([' ', '\t']);
+            return Some((name, value));
+        }
+        None
+    }
+}
+
+/// The header fields following the status line as bytes, which may not be utf8.
+/// Parsed like the built-in [`Fields`], also for the `httparse` backend when it can't parse a header.
+struct RawFields<'a> {
+    header: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RawFields<'a> {
+    fn new(header: &'a [u8]) -> Self {
+        let mut fields = Self { header, pos: 0 };
+        // skip the status line
+        fields.pos = fields.line_end(0).0;
+        fields
+    }
+
+    /// End of the line starting at `pos` without the line break, and the start of the next line
+    fn line_end(&self, pos: usize) -> (usize, usize) {
+        let end = self.header[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(self.header.len(), |idx| pos + idx);
+        let next = (end + 1).min(self.header.len());
+        let end = match self.header[..end].ends_with(b"\r") {
+            true => end - 1,
+            false => end,
+        };
+        (next, end)
+    }
+}
+
+/// Strips spaces and tabs from both ends
+fn trim_ows(mut bytes: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', rest @ ..] = bytes {
+        bytes = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = bytes {
+        bytes = rest;
+    }
+    bytes
+}
+
+impl<'a> Iterator for RawFields<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.header.len() {
+            let start = self.pos;
+            let (next, mut end) = self.line_end(start);
+            self.pos = next;
+
+            let Some(colon) = self.header[start..end].iter().position(|&b| b == b':') else {
+                continue;
+            };
+            // obsolete line folding continues the value on lines starting with whitespace
+            while self.header[self.pos..].starts_with(b" ")
+                || self.header[self.pos..].starts_with(b"\t")
+            {
+                (self.pos, end) = self.line_end(self.pos);
+            }
+
+            let name = trim_ows(&self.header[start..start + colon]);
+            let value = trim_ows(&self.header[start + colon + 1..end]);
+            return Some((name, value));
+        }
+        None
+    }
+}
+
+/// Number of header fields the `httparse` backend can parse
+#[cfg(feature = "httparse")]
+const MAX_HEADERS: usize = 64;
+
+/// The header fields following the status line as bytes, parsed by `httparse` into an array
+/// borrowing from the header. Falls back to the built-in [`RawFields`] if httparse rejects the
+/// status line, which is left to [`Response::status_code`], or there are more than
+/// [`MAX_HEADERS`] fields, so both backends accept the same responses.
+#[cfg(feature = "httparse")]
+struct ParsedFields<'a> {
+    headers: [httparse::Header<'a>; MAX_HEADERS],
+    len: usize,
+    pos: usize,
+    fallback: Option<RawFields<'a>>,
+}
+
+#[cfg(feature = "httparse")]
+impl<'a> ParsedFields<'a> {
+    fn parse(header: &'a [u8]) -> Result<Self> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut resp = httparse::Response::new(&mut headers);
+        let parsed = httparse::ParserConfig::default()
+            .allow_obsolete_multiline_headers_in_responses(true)
+            .allow_spaces_after_header_name_in_responses(true)
+            .ignore_invalid_headers_in_responses(true)
+            .parse_response(&mut resp, header);
+        let len = resp.headers.len();
+
+        let fallback = match parsed {
+            Ok(httparse::Status::Complete(_)) => None,
+            Err(
+                httparse::Error::Status
+                | httparse::Error::Version
+                | httparse::Error::TooManyHeaders,
+            ) => Some(RawFields::new(header)),
+            _ => return Err(ResponseError::Error),
+        };
+        Ok(Self {
+            headers,
+            len,
+            pos: 0,
+            fallback,
+        })
+    }
+}
+
+#[cfg(feature = "httparse")]
+impl<'a> Iterator for ParsedFields<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(fields) = &mut self.fallback {
+            return fields.next();
+        }
+        let header = self.headers[..self.len].get(self.pos)?;
+        self.pos += 1;
+        Some((header.name.as_bytes(), trim_ows(header.value)))
+    }
+}
+
+/// The header fields following the status line, as name and value without surrounding whitespace.
+/// Lines without a colon are skipped.
+#[cfg(feature = "httparse")]
+struct Fields<'a>(ParsedFields<'a>);
+
+#[cfg(feature = "httparse")]
+impl<'a> Fields<'a> {
+    fn parse(header: &'a str) -> Result<Self> {
+        ParsedFields::parse(header.as_bytes()).map(Self)
+    }
+}
+
+#[cfg(feature = "httparse")]
+impl<'a> Iterator for Fields<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the header is utf8 and only split at ascii bytes, so the parts are utf8 as well
+        let (name, value) = self.0.next()?;
+        Some((from_utf8(name).ok()?, from_utf8(value).ok()?))
+    }
+}
+
+/// Returns the line starting at `pos` (without CRLF) and the position after it
+fn line(buf: &[u8], pos: usize) -> Result<(&[u8], usize)> {
+    let rest = buf.get(pos..).ok_or(ResponseError::Incomplete)?;
+    let end = rest
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .ok_or(ResponseError::Incomplete)?;
+    Ok((&rest[..end], pos + end + 2))
+}
+
+/// Iterates over the data of each chunk in a chunked body, borrowing it from the buffer,
+/// stopping at the terminating chunk. The framing is decoded by [`ChunkedDecoder`].
+struct Chunks<'a> {
+    body: &'a [u8],
+    pos: usize,
+    decoder: ChunkedDecoder,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(body: &'a [u8]) -> Self {
+        Self {
+            body,
+            pos: 0,
+            decoder: ChunkedDecoder::new(),
+        }
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<&'a [u8]>> {
+        if self.decoder.chunks_done() {
+            return Ok(None);
+        }
+
+        let start = self.pos;
+        match self.decode()? {
+            Some(data) => Ok(Some(&self.body[start..][data])),
+            None if self.decoder.chunks_done() => Ok(None),
+            None => Err(ResponseError::Incomplete),
+        }
+    }
+
+    /// Decodes the rest of the body, up to the next piece of data or the trailers
+    fn decode(&mut self) -> Result<Option<core::ops::Range<usize>>> {
+        let (consumed, data) = self
+            .decoder
+            .next_data(&self.body[self.pos..])
+            .map_err(|_| ResponseError::InvalidChunk)?;
+        self.pos += consumed;
+        Ok(data)
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Result<&'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().transpose()
+    }
+}
+
+/// Calculates the length of a chunked body including all framing, the terminating chunk and trailers.
+fn chunked_len(body: &[u8]) -> Result<usize> {
+    let mut chunks = Chunks::new(body);
+    for chunk in chunks.by_ref() {
+        chunk?;
+    }
+
+    // skip trailers until the empty line
+    chunks.decode()?;
+    match chunks.decoder.is_done() {
+        true => Ok(chunks.pos),
+        false => Err(ResponseError::Incomplete),
+    }
+}
+
+#[cfg(feature = "unstable")]
+mod unstable {
+    use super::*;
+
+    impl core::error::Error for ResponseError {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            match self {
+                ResponseError::Utf8Error(e) => Some(e),
+                ResponseError::ParseIntError(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    const SIMPLE_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\ndate: Wed, 28 Sep 2022 08:23:31 GMT\r\n\r\n";
+    const BODY_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 132\r\nvary: Origin, Access-Control-Request-Method, Access-Control-Request-Headers\r\ncontent-type: application/json\r\ndate: Wed, 28 Sep 2022 09:00:53 GMT\r\n\r\n{\"status_code\":200,\"canonical_reason\":\"OK\",\"data\":\"tap.it backend built with rustc version 1.63.0 at 2022-09-05\",\"description\":null}";
+    const BODY_RESPONSE_2: &[u8] = b"HTTP/1.1 200 OK\r\nDate: Tue, 16 Apr 2024 11:18:11 GMT\r\nContent-Length: 36\r\nConnection: keep-alive\r\nvary: Origin, Access-Control-Request-Method, Access-Control-Request-Headers\r\n\r\n0ab5df47-4d09-493f-afa5-72f15d8edbc9";
+
+    const CHUNKED_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\ncontent-type: text/plain\r\n\r\n7\r\nchunked\r\nC;ext=1\r\n body, split\r\n0\r\n\r\n";
+
+    const SWITCHING_PROTOCOLS: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+
+    const NO_CONTENT: &[u8] = b"HTTP/1.1 204 No Content\r\nconnection: close\r\ndate: Wed, 30 Nov 2022 10:29:55 GMT\r\n\r\n";
+
+    #[test]
+    fn json_content_type() {
+        assert!(Response::new(BODY_RESPONSE).is_json().unwrap());
+        assert!(!Response::new(BODY_RESPONSE_2).is_json().unwrap());
+        assert!(!Response::new(CHUNKED_RESPONSE).is_json().unwrap());
+        assert!(Response::new(
+            b"HTTP/1.1 400 Bad Request\r\ncontent-type: application/problem+json; charset=utf-8\r\n\r\n"
+        )
+        .is_json()
+        .unwrap());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn deserialize_json() {
+        let body: serde_json::Value = Response::new(BODY_RESPONSE).json(true).unwrap();
+        assert_eq!(body["status_code"], 200);
+        assert_eq!(body["canonical_reason"], "OK");
+
+        let res = Response::new(BODY_RESPONSE_2).json::<serde_json::Value>(true);
+        assert!(matches!(
+            res,
+            Err(crate::Error::Response(ResponseError::UnexpectedContentType))
+        ));
+    }
+
+    #[cfg(feature = "serde_json_core")]
+    #[test]
+    fn deserialize_json_core() {
+        #[derive(serde_derive::Deserialize)]
+        struct Status<'a> {
+            status_code: u16,
+            canonical_reason: &'a str,
+        }
+
+        let body: Status = Response::new(BODY_RESPONSE).json_core(true).unwrap();
+        assert_eq!(body.status_code, 200);
+        assert_eq!(body.canonical_reason, "OK");
+    }
+
+    #[test]
+    fn deserialize_date() {
+        let mut resp = Response::new(SIMPLE_RESPONSE);
+        let mut resp2 = Response::new(BODY_RESPONSE_2);
+
+        let expected_date = NaiveDateTime::new(
+            chrono::NaiveDate::from_ymd_opt(2022, 9, 28).unwrap(),
+            chrono::NaiveTime::from_hms_opt(8, 23, 31).unwrap(),
+        )
+        .and_utc();
+
+        let date = resp.date().unwrap();
+        assert_eq!(date, expected_date);
+
+        let date = resp2.date().unwrap();
+        let expected_date = NaiveDateTime::new(
+            chrono::NaiveDate::from_ymd_opt(2024, 4, 16).unwrap(),
+            chrono::NaiveTime::from_hms_opt(11, 18, 11).unwrap(),
+        )
+        .and_utc();
+        assert_eq!(date, expected_date);
+    }
+
+    #[test]
+    fn deserialize_simple() {
+        let mut resp = Response::new(SIMPLE_RESPONSE);
+        assert_eq!(resp.status_code().unwrap(), 200);
+
+        assert_eq!(resp.content_length().unwrap(), 0);
+
+        println!("header: {}", resp.header().unwrap());
+        println!("body: {}", from_utf8(resp.body().unwrap()).unwrap());
+
+        assert_eq!(resp.content_type().unwrap(), None);
+
+        assert_eq!(resp.body().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn deserialize_body() {
+        let mut resp = Response::new(BODY_RESPONSE);
+        let header = resp.header().unwrap();
         let body = resp.body().unwrap();
 
         assert_eq!(resp.status_code().unwrap(), 200);
 
         assert_eq!(resp.content_length().unwrap(), 132);
 
-        assert_eq!(resp.content_type().unwrap(), Some("application/json"));
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(Mime::from_static("application/json"))
+        );
 
         println!("header: {}", header);
         println!("body: {}", from_utf8(body).unwrap());
@@ -369,12 +1840,935 @@ mod tests {
         assert!(resp.check().is_ok());
     }
 
+    #[test]
+    fn bodiless_responses() {
+        let mut resp = Response::new(b"HTTP/1.1 304 Not Modified\r\netag: \"1\"\r\n\r\n");
+        assert_eq!(resp.content_length().unwrap(), 0);
+
+        let not_modified = b"HTTP/1.1 304 Not Modified\r\ncontent-length: 5\r\n\r\nHTTP/";
+        let mut resp = Response::new(not_modified);
+        assert_eq!(resp.content_length_for(Method::Get).unwrap(), 0);
+        assert_eq!(
+            resp.total_len_for(Method::Get).unwrap(),
+            not_modified.len() - 5
+        );
+
+        let head = b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n";
+        let mut resp = Response::new(head);
+        assert_eq!(resp.content_length_for(Method::Head).unwrap(), 0);
+        assert_eq!(resp.total_len_for(Method::Head).unwrap(), head.len());
+        assert_eq!(
+            resp.total_len_for(Method::Get),
+            Err(ResponseError::Incomplete)
+        );
+
+        let mut buf = [0; 128];
+        let mut reader = ResponseReader::for_request(&head[..], &mut buf, Method::Head).unwrap();
+        assert_eq!(reader.next_body_bytes(&mut [0; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn websocket_handshake() {
+        let mut resp = Response::new(SWITCHING_PROTOCOLS);
+        assert!(resp.is_switching_protocols().unwrap());
+        assert_eq!(
+            resp.sec_websocket_accept().unwrap(),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+
+        let mut resp = Response::new(SIMPLE_RESPONSE);
+        assert!(!resp.is_switching_protocols().unwrap());
+        assert_eq!(
+            resp.sec_websocket_accept(),
+            Err(ResponseError::HeaderNotFound)
+        );
+    }
+
+    #[test]
+    fn body_with_malformed_status_line() {
+        let mut resp = Response::new(b"HTTP/1.1 2OO OK\r\ncontent-length: 2\r\n\r\nhi");
+        assert!(resp.status_code().is_err());
+        assert_eq!(resp.content_length().unwrap(), 2);
+        assert_eq!(resp.body().unwrap(), b"hi");
+
+        let mut resp = Response::new(b"HTTP/1.1 2OO OK\r\ncontent-length: 0\r\n\r\n");
+        assert_eq!(resp.body().unwrap(), b"");
+
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(resp.content_length(), Err(ResponseError::HeaderNotFound));
+    }
+
+    #[test]
+    fn content_length_whitespace_and_duplicates() {
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2 \r\n\r\nhi");
+        assert_eq!(resp.content_length().unwrap(), 2);
+        assert_eq!(resp.body().unwrap(), b"hi");
+
+        let mut resp =
+            Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\ncontent-length:2\r\n\r\nhi");
+        assert_eq!(resp.content_length().unwrap(), 2);
+
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2, 2\r\n\r\nhi");
+        assert_eq!(resp.entity_length().unwrap(), Some(2));
+
+        let mut resp =
+            Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 5\r\n\r\nhi");
+        assert_eq!(resp.content_length(), Err(ResponseError::Error));
+        assert_eq!(resp.body(), Err(ResponseError::Error));
+    }
+
+    #[test]
+    fn chunked_takes_precedence() {
+        for buf in [
+            &b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n"[..],
+            &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n2\r\nhi\r\n0\r\n\r\n"[..],
+        ] {
+            let mut resp = Response::new(buf);
+            #[cfg(not(feature = "strict"))]
+            {
+                assert_eq!(resp.content_length(), Err(ResponseError::UnsupportedEncoding));
+                assert_eq!(resp.body_ref().unwrap(), &b"hi"[..]);
+                assert_eq!(resp.total_len().unwrap(), buf.len());
+            }
+            #[cfg(feature = "strict")]
+            assert_eq!(resp.total_len(), Err(ResponseError::AmbiguousLength));
+        }
+    }
+
+    #[test]
+    fn content_range() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */500\r\n\r\n",
+        );
+        assert_eq!(
+            resp.content_range().unwrap(),
+            Some(ContentRange {
+                start: None,
+                end: None,
+                total: Some(500)
+            })
+        );
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-499/*\r\nContent-Length: 500\r\n\r\n",
+        );
+        assert_eq!(
+            resp.content_range().unwrap(),
+            Some(ContentRange {
+                start: Some(0),
+                end: Some(499),
+                total: None
+            })
+        );
+
+        for range in ["bytes */*", "bytes 5-4/10", "bytes 0-10/10", "items 0-1/2"] {
+            let buf =
+                std::format!("HTTP/1.1 206 Partial Content\r\nContent-Range: {range}\r\n\r\n");
+            assert!(
+                Response::new(buf.as_bytes()).content_range().is_err(),
+                "{}",
+                range
+            );
+        }
+        assert_eq!(Response::new(NO_CONTENT).content_range().unwrap(), None);
+    }
+
+    #[test]
+    fn location() {
+        let base = Uri::parse("https://example.com/files/a").unwrap();
+
+        let mut resp = Response::new(b"HTTP/1.1 302 Found\r\nLocation: //host/\r\n\r\n");
+        let target = resp.location(&base).unwrap().unwrap();
+        assert_eq!(target.as_str(), "https://host/");
+        assert_eq!(target.scheme(), "https");
+
+        let mut resp = Response::new(b"HTTP/1.1 301 Moved Permanently\r\nLocation: b?v=2\r\n\r\n");
+        assert_eq!(
+            resp.location(&base).unwrap().unwrap().as_str(),
+            "https://example.com/files/b?v=2"
+        );
+
+        let mut resp = Response::new(NO_CONTENT);
+        assert_eq!(resp.location(&base).unwrap(), None);
+    }
+
+    #[test]
+    fn content_type_bytes() {
+        let mut resp =
+            Response::new(b"HTTP/1.1 200 OK\r\nContent-Type:  text/plain;charset=\"a b\" \r\n\r\n");
+        assert_eq!(
+            resp.content_type_bytes().unwrap(),
+            Some(&b"text/plain;charset=\"a b\""[..])
+        );
+
+        let mut resp = Response::new(NO_CONTENT);
+        assert_eq!(resp.content_type_bytes().unwrap(), None);
+    }
+
+    #[test]
+    fn owned_response() {
+        fn fetch() -> OwnedResponse {
+            BODY_RESPONSE.to_vec().into()
+        }
+
+        let resp = fetch();
+        assert_eq!(resp.status_code().unwrap(), 200);
+        assert_eq!(
+            resp.body().unwrap(),
+            &BODY_RESPONSE[BODY_RESPONSE.len() - 132..]
+        );
+        assert_eq!(
+            resp.response().header_len(),
+            Response::new(BODY_RESPONSE).header_len()
+        );
+        assert_eq!(resp.into_vec(), BODY_RESPONSE);
+
+        let resp = OwnedResponse::new(b"HTTP/1.1 200 OK\r\n".to_vec());
+        assert_eq!(resp.status_code(), Err(ResponseError::Incomplete));
+    }
+
+    #[test]
+    fn leading_empty_lines() {
+        let buf = [b"\r\n\r\n\n", BODY_RESPONSE].concat();
+        let mut resp = Response::new_checked(&buf).unwrap();
+        assert_eq!(resp.status_code().unwrap(), 200);
+        assert_eq!(resp.content_length().unwrap(), 132);
+        assert_eq!(resp.header_len().unwrap(), BODY_RESPONSE.len() - 132 + 5);
+        assert!(resp.header().unwrap().starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(
+            resp.body().unwrap(),
+            &BODY_RESPONSE[BODY_RESPONSE.len() - 132..]
+        );
+
+        let pipelined = [SIMPLE_RESPONSE, b"\r\n", NO_CONTENT].concat();
+        let (_, rest) = Response::split_first(&pipelined).unwrap();
+        let (mut second, rest) = Response::split_first(rest).unwrap();
+        assert_eq!(second.status_code().unwrap(), 204);
+        assert!(rest.is_empty());
+
+        let mut resp = Response::new(b"\r\n\r\n");
+        assert_eq!(resp.header_len(), Err(ResponseError::Incomplete));
+    }
+
+    #[test]
+    fn truncated_status_line() {
+        for partial in [&b"HTTP/1.1 2"[..], b"HTTP/1.1 ", b"HTTP/1", b""] {
+            let mut resp = Response::new(partial);
+            assert_eq!(resp.status_code(), Err(ResponseError::Incomplete));
+            assert_eq!(
+                parse_status_line(partial),
+                Err(ResponseError::InvalidStatusLine)
+            );
+        }
+
+        let mut resp = Response::new(b"HTTP/1.1 2\r\n\r\n");
+        assert_eq!(resp.status_code(), Err(ResponseError::InvalidStatusLine));
+    }
+
+    /// Yields the input one byte per read
+    struct Trickle<'a>(&'a [u8]);
+
+    impl ErrorType for Trickle<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    /// Reads each piece in turn, returning no more than one piece per read
+    struct Pieces<'a, 'b>(&'a mut [&'b [u8]]);
+
+    impl ErrorType for Pieces<'_, '_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Pieces<'_, '_> {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            match self.0.iter_mut().find(|piece| !piece.is_empty()) {
+                Some(piece) => piece.read(buf),
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn read_from_reader() {
+        let mut buf = [0; 512];
+        let (mut resp, rest) = Response::read_from(Trickle(BODY_RESPONSE), &mut buf).unwrap();
+        assert_eq!(resp.status_code().unwrap(), 200);
+        assert!(rest.is_empty());
+        assert_eq!(
+            resp.body().unwrap(),
+            &BODY_RESPONSE[BODY_RESPONSE.len() - 132..]
+        );
+
+        let mut buf = [0; 512];
+        let pipelined = [CHUNKED_RESPONSE, NO_CONTENT].concat();
+        let (mut resp, rest) = Response::read_from(pipelined.as_slice(), &mut buf).unwrap();
+        assert_eq!(resp.body_ref().unwrap().as_ref(), b"chunked body, split");
+        assert_eq!(rest, NO_CONTENT);
+
+        // once the header is read, reads stop at the end of the body
+        let mut buf = [0; 512];
+        let header_len = Response::new(BODY_RESPONSE).header_len().unwrap();
+        let input = [BODY_RESPONSE, NO_CONTENT].concat();
+        let mut reader = [&input[..header_len], &input[header_len..]];
+        let (resp, rest) = Response::read_from(Pieces(&mut reader), &mut buf).unwrap();
+        assert_eq!(resp.inner, BODY_RESPONSE);
+        assert!(rest.is_empty());
+        assert_eq!(reader[1], NO_CONTENT);
+
+        let mut buf = [0; 512];
+        let unframed = b"HTTP/1.1 200 OK\r\nconnection: close\r\n\r\nuntil eof";
+        let (mut resp, _) = Response::read_from(Trickle(unframed), &mut buf).unwrap();
+        assert_eq!(resp.header_len().unwrap() + 9, resp.inner.len());
+
+        let mut buf = [0; 512];
+        let interim = [b"HTTP/1.1 100 Continue\r\n\r\n", NO_CONTENT].concat();
+        let (mut resp, _) = Response::read_from(Trickle(&interim), &mut buf).unwrap();
+        assert!(!resp.is_interim().unwrap());
+        assert_eq!(resp.inner, NO_CONTENT);
+
+        let mut buf = [0; 512];
+        assert!(matches!(
+            Response::read_from(&BODY_RESPONSE[..100], &mut buf),
+            Err(crate::Error::Response(ResponseError::Incomplete))
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn read_from_async_reader() {
+        let mut buf = [0; 512];
+        let pipelined = [BODY_RESPONSE, NO_CONTENT].concat();
+        let (mut resp, rest) =
+            crate::block_on(Response::read_from_async(pipelined.as_slice(), &mut buf)).unwrap();
+        assert_eq!(resp.status_code().unwrap(), 200);
+        assert_eq!(rest, NO_CONTENT);
+        assert_eq!(
+            resp.body().unwrap(),
+            &BODY_RESPONSE[BODY_RESPONSE.len() - 132..]
+        );
+
+        let mut buf = [0; 200];
+        assert!(matches!(
+            crate::block_on(Response::read_from_async(BODY_RESPONSE, &mut buf)),
+            Err(crate::Error::BufferTooSmall(200, _))
+        ));
+    }
+
+    #[test]
+    fn stream_body() {
+        let expected = &BODY_RESPONSE[BODY_RESPONSE.len() - 132..];
+        assert_eq!(
+            Response::new(BODY_RESPONSE)
+                .body_chunks(50)
+                .unwrap()
+                .map(<[u8]>::len)
+                .collect::<Vec<_>>(),
+            [50, 50, 32]
+        );
+
+        // the header buffer is smaller than the body
+        let mut buf = [0; 220];
+        let mut reader = ResponseReader::new(BODY_RESPONSE, &mut buf).unwrap();
+        assert_eq!(reader.response().status_code().unwrap(), 200);
+
+        let mut body = Vec::new();
+        let mut page = [0; 16];
+        loop {
+            match reader.next_body_bytes(&mut page).unwrap() {
+                0 => break,
+                n => body.extend_from_slice(&page[..n]),
+            }
+        }
+        assert_eq!(body, expected);
+
+        let mut buf = [0; 220];
+        let mut reader = ResponseReader::new(&BODY_RESPONSE[..200], &mut buf).unwrap();
+        assert!(matches!(
+            reader.next_body_bytes(&mut [0; 256]),
+            Ok(n) if n == 200 - reader.header_len
+        ));
+        assert!(matches!(
+            reader.next_body_bytes(&mut page),
+            Err(crate::Error::Response(ResponseError::Incomplete))
+        ));
+
+        // chunked bodies are decoded in place, whether the chunks trickle in or come at once
+        fn read_body<R: Read>(mut reader: ResponseReader<'_, R>) -> Vec<u8>
+        where
+            crate::Error: From<R::Error>,
+        {
+            let mut body = Vec::new();
+            let mut page = [0; 5];
+            loop {
+                match reader.next_body_bytes(&mut page).unwrap() {
+                    0 => return body,
+                    n => body.extend_from_slice(&page[..n]),
+                }
+            }
+        }
+        let mut buf = [0; 100];
+        let reader = ResponseReader::new(Trickle(CHUNKED_RESPONSE), &mut buf).unwrap();
+        assert_eq!(read_body(reader), b"chunked body, split");
+        let reader = ResponseReader::new(CHUNKED_RESPONSE, &mut buf).unwrap();
+        assert_eq!(read_body(reader), b"chunked body, split");
+
+        let mut buf = [0; 256];
+        let mut reader = ResponseReader::new(
+            &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nab"[..],
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(reader.next_body_bytes(&mut [0; 16]).unwrap(), 2);
+        assert!(matches!(
+            reader.next_body_bytes(&mut [0; 16]),
+            Err(crate::Error::Response(ResponseError::Incomplete))
+        ));
+    }
+
+    #[test]
+    fn read_from_buffer_too_small() {
+        let mut buf = [0; 200];
+        assert!(matches!(
+            Response::read_from(BODY_RESPONSE, &mut buf),
+            Err(crate::Error::BufferTooSmall(200, len)) if len == BODY_RESPONSE.len()
+        ));
+    }
+
+    #[test]
+    fn connection_close() {
+        assert!(!Response::new(BODY_RESPONSE).is_connection_close().unwrap());
+        assert!(Response::new(NO_CONTENT).is_connection_close().unwrap());
+        assert!(Response::new(b"HTTP/1.0 200 OK\r\n\r\n")
+            .is_connection_close()
+            .unwrap());
+        assert!(
+            !Response::new(b"HTTP/1.0 200 OK\r\nConnection: Keep-Alive\r\n\r\n")
+                .is_connection_close()
+                .unwrap()
+        );
+        assert!(
+            Response::new(b"HTTP/1.1 200 OK\r\nconnection: upgrade, Close\r\n\r\n")
+                .is_connection_close()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn www_authenticate() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"device\", charset=\"UTF-8\"\r\ncontent-length: 0\r\n\r\n",
+        );
+        let challenge = resp.www_authenticate().unwrap().unwrap();
+        assert_eq!(challenge.scheme, "Basic");
+        assert_eq!(challenge.realm(), Some("device"));
+        assert_eq!(challenge.param("charset"), Some("UTF-8"));
+
+        assert_eq!(Response::new(BODY_RESPONSE).www_authenticate(), Ok(None));
+    }
+
+    #[test]
+    fn status_line() {
+        assert_eq!(
+            parse_status_line(b"HTTP/1.1 200 OK\r\n").unwrap(),
+            (1, 1, 200, "OK")
+        );
+        assert_eq!(
+            parse_status_line(b"HTTP/1.0 404 Not Found").unwrap(),
+            (1, 0, 404, "Not Found")
+        );
+        assert_eq!(parse_status_line(b"HTTP/1.1 204").unwrap(), (1, 1, 204, ""));
+        assert_eq!(
+            parse_status_line(b"HTTP/1.1 204 ").unwrap(),
+            (1, 1, 204, "")
+        );
+
+        for line in [
+            &b"HTTP/1.1 2OO OK"[..],
+            b"HTTP/1.1 20",
+            b"HTTP/1.1 2000 OK",
+            b"HTTP/11 200 OK",
+            b"ICY 200 OK",
+            b"",
+        ] {
+            assert_eq!(
+                parse_status_line(line),
+                Err(ResponseError::InvalidStatusLine)
+            );
+        }
+    }
+
+    #[test]
+    fn unfold_header_value() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Security-Policy: default-src 'self';\r\n  img-src *;\r\n\tscript-src 'none'\r\nx-other: 1\r\ncontent-length: 0\r\n\r\n",
+        );
+
+        let csp = resp.header_value("content-security-policy").unwrap();
+        assert!(matches!(csp, Cow::Owned(_)));
+        assert_eq!(csp, "default-src 'self'; img-src *; script-src 'none'");
+
+        let other = resp.header_value("X-Other").unwrap();
+        assert!(matches!(other, Cow::Borrowed("1")));
+
+        assert_eq!(
+            resp.header_value("missing"),
+            Err(ResponseError::HeaderNotFound)
+        );
+        assert_eq!(resp.content_length().unwrap(), 0);
+    }
+
+    /// Both header parsing backends agree on these
+    #[test]
+    fn header_fields() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type :  text/plain \r\nnot a header\r\nX-Folded: a\r\n b\r\nX-Empty:\r\ncontent-length:\t2\r\n\r\nhi",
+        );
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(Mime::from_static("text/plain"))
+        );
+        assert_eq!(resp.header_value("x-folded").unwrap(), "a b");
+        assert_eq!(resp.header_value("x-empty").unwrap(), "");
+        assert_eq!(
+            resp.header_value("not a header"),
+            Err(ResponseError::HeaderNotFound)
+        );
+        assert_eq!(resp.body().unwrap(), b"hi");
+    }
+
+    #[test]
+    fn many_header_fields() {
+        let mut msg = std::string::String::from("HTTP/1.1 200 OK\r\n");
+        for i in 0..100 {
+            msg.push_str(&std::format!("x-field-{i}: {i}\r\n"));
+        }
+        msg.push_str("content-length: 2\r\n\r\nhi");
+
+        let mut resp = Response::new(msg.as_bytes());
+        assert_eq!(resp.headers().unwrap().count(), 101);
+        assert_eq!(resp.headers_bytes().unwrap().count(), 101);
+        assert_eq!(resp.header_value("x-field-99").unwrap(), "99");
+        assert_eq!(resp.body().unwrap(), b"hi");
+    }
+
+    #[test]
+    fn header_whitespace() {
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\ncontent-length:0\r\n\r\n");
+        assert_eq!(resp.content_length().unwrap(), 0);
+        assert!(resp.check().is_ok());
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type:text/plain\r\ncontent-length:  5\r\n\r\nhello",
+        );
+        assert_eq!(resp.content_length().unwrap(), 5);
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(Mime::from_static("text/plain"))
+        );
+        assert_eq!(resp.body().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn headers() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nServer: embedded\r\nX-Folded: a\r\n b\r\nContent-Length : 2 \r\n\r\nhi",
+        );
+        let headers = resp.headers().unwrap().collect::<std::vec::Vec<_>>();
+        assert_eq!(
+            headers,
+            [
+                ("Server", "embedded"),
+                ("X-Folded", "a\r\n b"),
+                ("Content-Length", "2")
+            ]
+        );
+        let raw = resp.headers_bytes().unwrap().collect::<std::vec::Vec<_>>();
+        assert_eq!(raw.len(), 3);
+        for ((name, value), (raw_name, raw_value)) in headers.into_iter().zip(raw) {
+            assert_eq!((name.as_bytes(), value.as_bytes()), (raw_name, raw_value));
+        }
+
+        let mut resp =
+            Response::new(b"HTTP/1.1 200 OK\r\nX-Token: \xff\xfe\t\r\nContent-Length: 0\r\n\r\n");
+        assert!(resp.headers().is_err());
+        assert_eq!(
+            resp.headers_bytes().unwrap().collect::<std::vec::Vec<_>>(),
+            [
+                (b"X-Token".as_slice(), b"\xff\xfe".as_slice()),
+                (b"Content-Length".as_slice(), b"0".as_slice())
+            ]
+        );
+        assert_eq!(resp.status_code().unwrap(), 200);
+        assert!(matches!(
+            resp.content_length(),
+            Err(ResponseError::Utf8Error(_))
+        ));
+    }
+
+    #[test]
+    fn text() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 11\r\n\r\n{\"a\": \"\xc3\xa6\"}",
+        );
+        assert_eq!(resp.text().unwrap(), Some("{\"a\": \"\u{e6}\"}"));
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 2\r\n\r\nhi",
+        );
+        assert_eq!(resp.text().unwrap(), None);
+        assert_eq!(resp.body_as_str().unwrap(), "hi");
+
+        for ct in [
+            "text/csv",
+            "application/atom+xml",
+            "application/x-foo; charset=utf-8",
+        ] {
+            let buf = std::format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {ct}\r\nContent-Length: 2\r\n\r\nhi"
+            );
+            assert_eq!(
+                Response::new(buf.as_bytes()).text().unwrap(),
+                Some("hi"),
+                "{}",
+                ct
+            );
+        }
+
+        // invalid utf8 isn't text, whatever the content type says
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n\xff\xfe",
+        );
+        assert_eq!(resp.text().unwrap(), None);
+        assert_eq!(Response::new(NO_CONTENT).text().unwrap(), None);
+    }
+
+    #[test]
+    fn padded_values() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nETag: \"abc\" \r\nContent-Type:\t application/json \t\r\nContent-Length:  2 \r\nDate: Wed, 21 Oct 2015 07:28:00 GMT  \r\nLocation: /next \r\n\r\n{}",
+        );
+        assert_eq!(resp.header_value("etag").unwrap(), "\"abc\"");
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(crate::mime::APPLICATION_JSON)
+        );
+        assert_eq!(
+            resp.content_type_bytes().unwrap(),
+            Some(b"application/json".as_slice())
+        );
+        assert_eq!(resp.content_length().unwrap(), 2);
+        assert_eq!(resp.date().unwrap().timestamp(), 1445412480);
+        let base = Uri::parse("http://test.com/a").unwrap();
+        assert_eq!(
+            resp.location(&base).unwrap().unwrap().as_str(),
+            "http://test.com/next"
+        );
+        assert_eq!(resp.body().unwrap(), b"{}");
+
+        let owned = OwnedResponse::from(resp.header_bytes().unwrap().to_vec());
+        assert_eq!(owned.header_value("ETag").unwrap(), "\"abc\"");
+    }
+
+    #[test]
+    fn continue_before_final() {
+        let mut buf = std::vec::Vec::from(b"HTTP/1.1 100 Continue\r\n\r\n".as_slice());
+        buf.extend_from_slice(BODY_RESPONSE);
+
+        let (mut interim, rest) = Response::split_first(&buf).unwrap();
+        assert!(interim.is_continue().unwrap());
+        assert_eq!(interim.body().unwrap(), b"");
+
+        let mut resp = Response::new_checked(rest).unwrap();
+        assert!(!resp.is_continue().unwrap());
+        assert_eq!(resp.status_code().unwrap(), 200);
+    }
+
     #[test]
     fn test_no_incomplete() {
         let resp = Response::new(&NO_CONTENT[0..NO_CONTENT.len() - 1]);
         assert_eq!(resp.check(), Err(ResponseError::Incomplete));
     }
 
+    #[test]
+    fn split_pipelined() {
+        let mut buf = std::vec::Vec::new();
+        buf.extend_from_slice(BODY_RESPONSE);
+        buf.extend_from_slice(CHUNKED_RESPONSE);
+        buf.extend_from_slice(NO_CONTENT);
+
+        let (mut first, rest) = Response::split_first(&buf).unwrap();
+        assert_eq!(first.body().unwrap().len(), 132);
+
+        let (mut second, rest) = Response::split_first(rest).unwrap();
+        assert!(second.is_chunked().unwrap());
+        assert_eq!(rest, NO_CONTENT);
+
+        let (mut third, rest) = Response::split_first(rest).unwrap();
+        assert_eq!(third.status_code().unwrap(), 204);
+        assert!(rest.is_empty());
+
+        assert_eq!(
+            Response::split_first(&CHUNKED_RESPONSE[..CHUNKED_RESPONSE.len() - 2]),
+            Err(ResponseError::Incomplete)
+        );
+    }
+
+    #[test]
+    fn body_ref_chunked() {
+        let mut resp = Response::new(BODY_RESPONSE);
+        assert!(matches!(resp.body_ref().unwrap(), Cow::Borrowed(b) if b.len() == 132));
+
+        let mut resp = Response::new(CHUNKED_RESPONSE);
+        let body = resp.body_ref().unwrap();
+        assert!(matches!(body, Cow::Owned(_)));
+        assert_eq!(body.as_ref(), b"chunked body, split");
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        );
+        assert!(matches!(resp.body_ref().unwrap(), Cow::Borrowed(b"hello")));
+    }
+
+    #[test]
+    fn build_response() {
+        let buf = ResponseBuilder::status(StatusCode::NOT_FOUND)
+            .insert_header((crate::header::CONNECTION.clone(), "close".into()))
+            .body("not here")
+            .to_vec()
+            .unwrap();
+
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let mut parsed = httparse::Response::new(&mut headers);
+        assert!(parsed.parse(&buf).unwrap().is_complete());
+        assert_eq!(parsed.code, Some(404));
+        assert_eq!(parsed.reason, Some("Not Found"));
+
+        let mut resp = Response::new_checked(&buf).unwrap();
+        assert_eq!(resp.status_code().unwrap(), 404);
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(Mime::from_static("text/plain; charset=utf-8"))
+        );
+        assert_eq!(resp.body_as_str().unwrap(), "not here");
+
+        let buf = ResponseBuilder::status(StatusCode::NO_CONTENT)
+            .to_vec()
+            .unwrap();
+        assert_eq!(buf, b"HTTP/1.1 204 No Content\r\n\r\n");
+    }
+
+    #[test]
+    fn build_response_headers() {
+        let buf = ResponseBuilder::sse()
+            .insert_header((
+                crate::header::CONTENT_TYPE.clone(),
+                HeaderValue::from_static(b"text/event-stream; charset=utf-8"),
+            ))
+            .to_vec()
+            .unwrap();
+        let mut resp = Response::new(&buf);
+        let content_types = resp
+            .headers()
+            .unwrap()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+            .count();
+        assert_eq!(content_types, 1);
+        assert_eq!(
+            resp.header_value("content-type").unwrap(),
+            "text/event-stream; charset=utf-8"
+        );
+
+        let buf = ResponseBuilder::status(StatusCode::OK)
+            .append_header((crate::header::SET_COOKIE.clone(), "a=1".into()))
+            .append_header((crate::header::SET_COOKIE.clone(), "b=2".into()))
+            .to_vec()
+            .unwrap();
+        let mut resp = Response::new(&buf);
+        assert_eq!(resp.headers().unwrap().count(), 2);
+
+        let res = ResponseBuilder::status(StatusCode::OK)
+            .insert_header((HeaderKey::from_unchecked("x bad"), "1".into()))
+            .append_header((crate::header::CONNECTION.clone(), "close\r\nx: 1".into()))
+            .to_vec();
+        assert!(matches!(res, Err(crate::Error::InvalidHeaderKey)));
+    }
+
+    #[test]
+    fn custom_reason() {
+        let buf = ResponseBuilder::status(StatusCode::SERVICE_UNAVAILABLE)
+            .reason("Sensor Warming Up")
+            .to_vec()
+            .unwrap();
+        assert_eq!(buf, b"HTTP/1.1 503 Sensor Warming Up\r\n\r\n");
+
+        let res = ResponseBuilder::status(StatusCode::OK)
+            .reason("OK\r\nx-injected: 1")
+            .to_vec();
+        assert!(matches!(res, Err(crate::Error::InvalidHeaderValue)));
+    }
+
+    #[test]
+    fn chunked_trailers() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: x-checksum, x-count\r\n\r\n5\r\nhello\r\n0\r\nx-checksum: abcd \r\nX-Count:1\r\n\r\n",
+        );
+        assert_eq!(resp.body_ref().unwrap().as_ref(), b"hello");
+
+        let trailers = resp.trailers().unwrap().collect::<std::vec::Vec<_>>();
+        assert_eq!(trailers, [("x-checksum", "abcd"), ("X-Count", "1")]);
+
+        let mut resp = Response::new(CHUNKED_RESPONSE);
+        assert_eq!(resp.trailers().unwrap().count(), 0);
+
+        let mut resp = Response::new(BODY_RESPONSE);
+        assert_eq!(resp.trailers().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn invalid_chunk() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\nhello\r\n0\r\n\r\n",
+        );
+        assert_eq!(resp.body_ref(), Err(ResponseError::InvalidChunk));
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhello\r\n0\r\n\r\n",
+        );
+        assert_eq!(resp.body_ref(), Err(ResponseError::InvalidChunk));
+    }
+
+    #[test]
+    fn oversized_chunk() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nab\r\n0\r\n\r\n",
+        );
+        assert_eq!(resp.total_len(), Err(ResponseError::InvalidChunk));
+        assert_eq!(resp.body_ref(), Err(ResponseError::InvalidChunk));
+    }
+
+    #[test]
+    fn body_truncated() {
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello");
+        assert_eq!(resp.body(), Err(ResponseError::Incomplete));
+        assert_eq!(resp.body_ref(), Err(ResponseError::Incomplete));
+
+        let mut resp =
+            Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nhello");
+        assert_eq!(resp.body(), Err(ResponseError::Incomplete));
+        assert_eq!(resp.total_len(), Err(ResponseError::Incomplete));
+    }
+
+    #[test]
+    fn total_len() {
+        let no_content = b"HTTP/1.1 204 No Content\r\n\r\n";
+        assert_eq!(Response::new(no_content).total_len(), Ok(no_content.len()));
+
+        let chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhe\r\n0\r\n\r\n";
+        let buf = [&chunked[..], b"HTTP/1.1 200 OK"].concat();
+        assert_eq!(Response::new(&buf).total_len(), Ok(chunked.len()));
+
+        let truncated = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhel";
+        assert_eq!(
+            Response::new(truncated).total_len(),
+            Err(ResponseError::Incomplete)
+        );
+    }
+
+    #[test]
+    fn header_scan_resumes() {
+        let msg = b"HTTP/1.1 204 No Content\r\nServer: x\r\n\r\nrest";
+        let header_len = msg.len() - 4;
+
+        let mut scan = HeaderScan::new();
+        for end in 0..header_len {
+            assert_eq!(scan.header_len(&msg[..end]), Err(ResponseError::Incomplete));
+            assert_eq!(scan.scanned(), end);
+        }
+        assert_eq!(scan.header_len(&msg[..header_len]), Ok(header_len));
+        assert_eq!(scan.header_len(msg), Ok(header_len));
+
+        let mut resp = Response::with_header_scan(&msg[..header_len], scan);
+        assert_eq!(resp.status_code().unwrap(), 204);
+
+        // the header may not be utf8, which only the accessors needing strings check
+        let mut scan = HeaderScan::new();
+        assert!(scan.header_len(b"HTTP/1.1 200 OK\r\nx: \xff\r\n").is_err());
+        assert_eq!(
+            scan.header_len(b"HTTP/1.1 200 OK\r\nx: \xff\r\n\r\n"),
+            Ok(25)
+        );
+    }
+
+    #[test]
+    fn parse_all() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello",
+        );
+        let parsed = resp.parse_all().unwrap();
+        assert_eq!(
+            parsed,
+            ParsedResponse {
+                status_code: StatusCode::OK,
+                content_length: Some(5),
+                content_type: Some(Mime::from_static("text/plain")),
+                header_len: 64,
+                body: Cow::Borrowed(b"hello"),
+            }
+        );
+
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhe\r\n3\r\nllo\r\n0\r\n\r\n",
+        );
+        let parsed = resp.parse_all().unwrap();
+        assert_eq!(parsed.content_length, None);
+        assert_eq!(parsed.content_type, None);
+        assert_eq!(parsed.body.as_ref(), b"hello");
+
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n");
+        assert_eq!(resp.parse_all(), Err(ResponseError::Incomplete));
+    }
+
+    #[test]
+    fn allowed_methods() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD,OPTIONS, BREW\r\nContent-Length: 0\r\n\r\n",
+        );
+        assert_eq!(
+            resp.allowed_methods().unwrap().collect::<Vec<_>>(),
+            [Method::Get, Method::Head, Method::Options]
+        );
+
+        let mut resp = Response::new(b"HTTP/1.1 204 No Content\r\n\r\n");
+        assert_eq!(resp.allowed_methods().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn entity_length() {
+        let mut resp = Response::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4096\r\nContent-Type: image/png\r\n\r\n",
+        );
+        assert_eq!(resp.entity_length().unwrap(), Some(4096));
+
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        assert_eq!(resp.entity_length().unwrap(), None);
+
+        let mut resp = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: big\r\n\r\n");
+        assert!(resp.entity_length().is_err());
+    }
+
     #[test]
     fn checked_deserialize_body() {
         let mut resp = Response::new(BODY_RESPONSE);
@@ -389,7 +2783,10 @@ mod tests {
 
         assert_eq!(resp.content_length().unwrap(), 132);
 
-        assert_eq!(resp.content_type().unwrap(), Some("application/json"));
+        assert_eq!(
+            resp.content_type().unwrap(),
+            Some(Mime::from_static("application/json"))
+        );
 
         println!("header: {}", header);
         println!("body: {}", from_utf8(body).unwrap());
@@ -0,0 +1,123 @@
+//! Server-sent events (`text/event-stream`), streamed after the header written by
+//! [`crate::response::ResponseBuilder::sse`].
+
+use embedded_io::{ErrorType, Write};
+
+use crate::{Error, Result};
+
+/// A single event, written as its `id`, `event` and `data` fields followed by an empty line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SseEvent<'a> {
+    pub id: Option<&'a str>,
+    /// The event type, browsers dispatch events without one as `message`
+    pub event: Option<&'a str>,
+    /// Sent as one `data` field per line
+    pub data: &'a str,
+}
+
+impl<'a> SseEvent<'a> {
+    pub fn new(data: &'a str) -> Self {
+        Self {
+            id: None,
+            event: None,
+            data,
+        }
+    }
+
+    pub fn id(mut self, id: &'a str) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn event(mut self, event: &'a str) -> Self {
+        self.event = Some(event);
+        self
+    }
+
+    /// Writes the event. Fails with `Error::InvalidHeaderValue` if `id` or `event` contain
+    /// a line break, which would end the field early.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()>
+    where
+        Error: From<<W as ErrorType>::Error>,
+    {
+        for (name, value) in [("id", self.id), ("event", self.event)] {
+            let Some(value) = value else {
+                continue;
+            };
+            if value.contains(['\r', '\n']) {
+                return Err(Error::InvalidHeaderValue);
+            }
+            write_field(&mut w, name, value)?;
+        }
+
+        // CRLF, LF and a lone CR all end a line
+        for line in self
+            .data
+            .split('\n')
+            .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+        {
+            write_field(&mut w, "data", line)?;
+        }
+
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SseEvent<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "SseEvent {{ id: {}, event: {}, data: {=str} }}",
+            self.id,
+            self.event,
+            self.data
+        );
+    }
+}
+
+fn write_field<W: Write>(w: &mut W, name: &str, value: &str) -> Result<()>
+where
+    Error: From<<W as ErrorType>::Error>,
+{
+    w.write_all(name.as_bytes())?;
+    w.write_all(b": ")?;
+    w.write_all(value.as_bytes())?;
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ResponseBuilder;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn stream() {
+        let mut buf = ResponseBuilder::sse().to_vec().unwrap();
+        assert_eq!(
+            buf,
+            b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncache-control: no-cache\r\n\r\n"
+        );
+
+        buf.clear();
+        SseEvent::new("21.5").write_to(&mut buf).unwrap();
+        SseEvent::new("{\"t\":21.5}\n{\"h\":40}")
+            .event("reading")
+            .id("7")
+            .write_to(&mut buf)
+            .unwrap();
+        assert_eq!(
+            buf,
+            b"data: 21.5\n\nid: 7\nevent: reading\ndata: {\"t\":21.5}\ndata: {\"h\":40}\n\n"
+        );
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            SseEvent::new("x").event("a\nb").write_to(&mut buf),
+            Err(Error::InvalidHeaderValue)
+        ));
+    }
+}
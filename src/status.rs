@@ -0,0 +1,222 @@
+//! Status code helpers.
+
+use core::fmt::Display;
+
+/// A status code between 100 and 999, as returned by [`crate::response::Response::status_code`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    pub const CONTINUE: StatusCode = StatusCode(100);
+    pub const SWITCHING_PROTOCOLS: StatusCode = StatusCode(101);
+    pub const OK: StatusCode = StatusCode(200);
+    pub const CREATED: StatusCode = StatusCode(201);
+    pub const ACCEPTED: StatusCode = StatusCode(202);
+    pub const NO_CONTENT: StatusCode = StatusCode(204);
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
+    pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
+    pub const FOUND: StatusCode = StatusCode(302);
+    pub const SEE_OTHER: StatusCode = StatusCode(303);
+    pub const NOT_MODIFIED: StatusCode = StatusCode(304);
+    pub const TEMPORARY_REDIRECT: StatusCode = StatusCode(307);
+    pub const PERMANENT_REDIRECT: StatusCode = StatusCode(308);
+    pub const BAD_REQUEST: StatusCode = StatusCode(400);
+    pub const UNAUTHORIZED: StatusCode = StatusCode(401);
+    pub const FORBIDDEN: StatusCode = StatusCode(403);
+    pub const NOT_FOUND: StatusCode = StatusCode(404);
+    pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode(405);
+    pub const REQUEST_TIMEOUT: StatusCode = StatusCode(408);
+    pub const CONFLICT: StatusCode = StatusCode(409);
+    pub const CONTENT_TOO_LARGE: StatusCode = StatusCode(413);
+    pub const TOO_MANY_REQUESTS: StatusCode = StatusCode(429);
+    pub const INTERNAL_SERVER_ERROR: StatusCode = StatusCode(500);
+    pub const NOT_IMPLEMENTED: StatusCode = StatusCode(501);
+    pub const BAD_GATEWAY: StatusCode = StatusCode(502);
+    pub const SERVICE_UNAVAILABLE: StatusCode = StatusCode(503);
+    pub const GATEWAY_TIMEOUT: StatusCode = StatusCode(504);
+
+    /// Returns `None` for codes outside of 100 to 999
+    pub const fn from_u16(code: u16) -> Option<Self> {
+        match code {
+            100..=999 => Some(StatusCode(code)),
+            _ => None,
+        }
+    }
+
+    pub const fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    /// The canonical reason phrase, see [`reason_phrase`]
+    pub fn reason(&self) -> Option<&'static str> {
+        reason_phrase(self.0)
+    }
+
+    /// `1xx`
+    pub const fn is_informational(&self) -> bool {
+        matches!(self.0, 100..=199)
+    }
+
+    /// `2xx`
+    pub const fn is_success(&self) -> bool {
+        matches!(self.0, 200..=299)
+    }
+
+    /// `3xx`
+    pub const fn is_redirection(&self) -> bool {
+        matches!(self.0, 300..=399)
+    }
+
+    /// `4xx`
+    pub const fn is_client_error(&self) -> bool {
+        matches!(self.0, 400..=499)
+    }
+
+    /// `5xx`
+    pub const fn is_server_error(&self) -> bool {
+        matches!(self.0, 500..=599)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(code: StatusCode) -> Self {
+        code.0
+    }
+}
+
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<StatusCode> for u16 {
+    fn eq(&self, other: &StatusCode) -> bool {
+        *self == other.0
+    }
+}
+
+/// Prints the code followed by its reason phrase if it has one, like `200 OK`
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.reason() {
+            Some(reason) => write!(f, "{} {}", self.0, reason),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for StatusCode {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=u16}", self.0);
+    }
+}
+
+/// Returns the canonical reason phrase of a registered status code,
+/// or `None` if the code is unknown.
+pub fn reason_phrase(code: u16) -> Option<&'static str> {
+    Some(match code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        102 => "Processing",
+        103 => "Early Hints",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "Non-Authoritative Information",
+        204 => "No Content",
+        205 => "Reset Content",
+        206 => "Partial Content",
+        207 => "Multi-Status",
+        208 => "Already Reported",
+        226 => "IM Used",
+        300 => "Multiple Choices",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        305 => "Use Proxy",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        407 => "Proxy Authentication Required",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        417 => "Expectation Failed",
+        418 => "I'm a teapot",
+        421 => "Misdirected Request",
+        422 => "Unprocessable Content",
+        423 => "Locked",
+        424 => "Failed Dependency",
+        425 => "Too Early",
+        426 => "Upgrade Required",
+        428 => "Precondition Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        451 => "Unavailable For Legal Reasons",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        506 => "Variant Also Negotiates",
+        507 => "Insufficient Storage",
+        508 => "Loop Detected",
+        510 => "Not Extended",
+        511 => "Network Authentication Required",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_codes() {
+        assert_eq!(reason_phrase(100), Some("Continue"));
+        assert_eq!(reason_phrase(200), Some("OK"));
+        assert_eq!(reason_phrase(203), Some("Non-Authoritative Information"));
+        assert_eq!(reason_phrase(404), Some("Not Found"));
+        assert_eq!(reason_phrase(511), Some("Network Authentication Required"));
+    }
+
+    #[test]
+    fn status_code() {
+        let code = StatusCode::from_u16(404).unwrap();
+        assert_eq!(code, StatusCode::NOT_FOUND);
+        assert_eq!(code, 404);
+        assert_eq!(u16::from(code), 404);
+        assert!(code.is_client_error() && !code.is_success());
+        assert_eq!(std::format!("{}", code), "404 Not Found");
+        assert_eq!(
+            std::format!("{}", StatusCode::from_u16(299).unwrap()),
+            "299"
+        );
+
+        assert_eq!(StatusCode::from_u16(99), None);
+        assert_eq!(StatusCode::from_u16(1000), None);
+    }
+
+    #[test]
+    fn unknown_codes() {
+        assert_eq!(reason_phrase(0), None);
+        assert_eq!(reason_phrase(299), None);
+        assert_eq!(reason_phrase(600), None);
+    }
+}
@@ -1,49 +1,234 @@
 use crate::{Error, Result};
 
 use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::ops::Range;
 
+/// Reason a uri failed to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UriError {
+    /// The uri doesn't start with `<scheme>://`
+    MissingScheme,
+    /// There is nothing between `://` and the path
+    EmptyAuthority,
+    /// The port isn't a number in the range of `u16`
+    InvalidPort,
+    /// A `%` isn't followed by two hex digits
+    InvalidPercentEncoding,
+    /// An IP literal isn't closed by `]`, or is followed by something other than a port
+    InvalidHost,
+    /// The target passed to [`Uri::parse_origin_form`] doesn't start with `/` or has a fragment
+    InvalidOriginForm,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for UriError {
+    fn format(&self, fmt: defmt::Formatter) {
+        match self {
+            UriError::MissingScheme => defmt::write!(fmt, "MissingScheme"),
+            UriError::EmptyAuthority => defmt::write!(fmt, "EmptyAuthority"),
+            UriError::InvalidPort => defmt::write!(fmt, "InvalidPort"),
+            UriError::InvalidPercentEncoding => defmt::write!(fmt, "InvalidPercentEncoding"),
+            UriError::InvalidHost => defmt::write!(fmt, "InvalidHost"),
+            UriError::InvalidOriginForm => defmt::write!(fmt, "InvalidOriginForm"),
+        }
+    }
+}
+
+impl core::fmt::Display for UriError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Uri<'a> {
     pub inner: Cow<'a, str>,
     scheme: Range<usize>,
     authority: Range<usize>,
     path_and_query: Range<usize>,
+    /// the path part of `path_and_query`, the query follows after a `?`
+    path: Range<usize>,
+}
+
+/// Splits an authority into the host, without the brackets of an IP literal, and the port if any
+fn split_host_port(authority: &str) -> core::result::Result<(&str, Option<&str>), UriError> {
+    // skip userinfo
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+
+    // the colons inside an ip literal aren't port separators
+    if let Some(literal) = host_port.strip_prefix('[') {
+        let (host, rest) = literal.split_once(']').ok_or(UriError::InvalidHost)?;
+        return match rest {
+            "" => Ok((host, None)),
+            _ => Ok((
+                host,
+                Some(rest.strip_prefix(':').ok_or(UriError::InvalidHost)?),
+            )),
+        };
+    }
+
+    Ok(match host_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_port, None),
+    })
+}
+
+/// Removes `.` and `..` segments from an absolute path (RFC 3986 section 5.2.4)
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+
+    for segment in path.trim_start_matches('/').split('/') {
+        trailing_slash = matches!(segment, "." | "..");
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut out = String::from("/");
+    out.push_str(&segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        out.push('/');
+    }
+    out
+}
+
+/// Decodes `%XX` escapes and `+` as space, as in `application/x-www-form-urlencoded`.
+/// Invalid escapes are kept as they are and invalid utf8 is replaced.
+/// Only allocates if there is something to decode.
+fn form_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains(['%', '+']) {
+        return Cow::Borrowed(s);
+    }
+
+    let hex = |b: u8| (b as char).to_digit(16);
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'+' => decoded.push(b' '),
+            b'%' => match (
+                bytes.get(idx + 1).and_then(|&b| hex(b)),
+                bytes.get(idx + 2).and_then(|&b| hex(b)),
+            ) {
+                (Some(hi), Some(lo)) => {
+                    decoded.push((hi * 16 + lo) as u8);
+                    idx += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
+        }
+        idx += 1;
+    }
+
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Checks that all `%` are followed by two hex digits
+fn valid_percent_encoding(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    bytes.iter().enumerate().all(|(idx, &b)| {
+        b != b'%'
+            || bytes
+                .get(idx + 1..idx + 3)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+    })
 }
 
 impl<'a> Uri<'a> {
+    /// Parses an absolute uri like `http://host/path?query`.
+    /// An empty path, as in `http://host?x=1`, is normalized to `/`.
     pub fn parse<S: Into<Cow<'a, str>>>(uri: S) -> Result<Self> {
         let mut start_idx = 0;
 
         let s = uri.into();
 
         let scheme = match s.find("://") {
-            Some(idx) => {
+            Some(idx) if idx > 0 => {
                 let scheme = start_idx..idx;
                 start_idx = idx + 3;
                 scheme
             }
-            None => return Err(Error::InvalidUri)
+            _ => return Err(UriError::MissingScheme.into()),
         };
 
-        let authority = match s[start_idx..].find('/') {
-            Some(idx) => {
-                let authority = start_idx..start_idx + idx;
-                start_idx += idx;
-                authority
-            }
-            None => {
-                return Err(Error::InvalidUri);
+        // the authority ends with the path, the query, the fragment or the uri
+        let authority = match s[start_idx..].find(['/', '?', '#']) {
+            Some(0) => return Err(UriError::EmptyAuthority.into()),
+            Some(idx) => start_idx..start_idx + idx,
+            None if start_idx == s.len() => return Err(UriError::EmptyAuthority.into()),
+            None => start_idx..s.len(),
+        };
+        start_idx = authority.end;
+
+        // an empty path is normalized to `/`, which needs a copy of a borrowed uri
+        let s = match s[start_idx..].starts_with('/') {
+            true => s,
+            false => {
+                let mut owned = s.into_owned();
+                owned.insert(start_idx, '/');
+                Cow::Owned(owned)
             }
         };
 
-        let path_and_query = start_idx..s.len();
+        // an empty port is allowed by RFC 3986
+        match split_host_port(&s[authority.clone()])? {
+            (_, Some(port)) if !port.is_empty() && port.parse::<u16>().is_err() => {
+                return Err(UriError::InvalidPort.into());
+            }
+            _ => {}
+        }
+
+        if !valid_percent_encoding(&s[start_idx..]) {
+            return Err(UriError::InvalidPercentEncoding.into());
+        }
+
+        // the fragment isn't part of the request target
+        let path_and_query = start_idx
+            ..s[start_idx..]
+                .find('#')
+                .map_or(s.len(), |idx| start_idx + idx);
+        let path = start_idx
+            ..s[path_and_query.clone()]
+                .find('?')
+                .map_or(path_and_query.end, |idx| start_idx + idx);
 
         Ok(Self {
             inner: s,
             scheme,
             authority,
             path_and_query,
+            path,
+        })
+    }
+
+    /// Parses an origin-form request target like `/v1/health?verbose=1`, for requests to a host
+    /// known out of band. The scheme and authority are empty, and the whole target is the path and
+    /// query. No `Host` is sent for such a uri, set it as a header on the request instead.
+    pub fn parse_origin_form<S: Into<Cow<'a, str>>>(target: S) -> Result<Self> {
+        let s = target.into();
+
+        if !s.starts_with('/') || s.contains('#') {
+            return Err(UriError::InvalidOriginForm.into());
+        }
+        if !valid_percent_encoding(&s) {
+            return Err(UriError::InvalidPercentEncoding.into());
+        }
+
+        Ok(Self {
+            scheme: 0..0,
+            authority: 0..0,
+            path_and_query: 0..s.len(),
+            path: 0..s.find('?').unwrap_or(s.len()),
+            inner: s,
         })
     }
 
@@ -53,6 +238,7 @@ impl<'a> Uri<'a> {
             scheme: self.scheme.clone(),
             authority: self.authority.clone(),
             path_and_query: self.path_and_query.clone(),
+            path: self.path.clone(),
         }
     }
 
@@ -62,9 +248,14 @@ impl<'a> Uri<'a> {
             scheme: self.scheme.clone(),
             authority: self.authority.clone(),
             path_and_query: self.path_and_query.clone(),
+            path: self.path.clone(),
         }
     }
 
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
     pub fn scheme(&self) -> &str {
         &self.inner[self.scheme.clone()]
     }
@@ -73,11 +264,135 @@ impl<'a> Uri<'a> {
         &self.inner[self.authority.clone()]
     }
 
+    /// The path and query sent as the request target, without the fragment
     pub fn path_and_query(&self) -> &str {
         &self.inner[self.path_and_query.clone()]
     }
+
+    /// The path, without the query
+    pub fn path(&self) -> &str {
+        &self.inner[self.path.clone()]
+    }
+
+    /// The query after the `?`, if any
+    pub fn query(&self) -> Option<&str> {
+        self.inner[self.path.end..self.path_and_query.end].strip_prefix('?')
+    }
+
+    /// Iterates over the `key=value` pairs of the query, percent decoded and with `+` decoded as space.
+    /// Keys without a value, like `flag` in `?flag&a=1`, have an empty value.
+    pub fn query_pairs(&self) -> impl Iterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        self.query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (form_decode(key), form_decode(value))
+            })
+    }
+
+    /// The fragment after the `#`, if any
+    pub fn fragment(&self) -> Option<&str> {
+        self.inner[self.path_and_query.end..].strip_prefix('#')
+    }
+
+    /// Resolves a reference, like the `Location` of a redirect, against this uri (RFC 3986 section 5.2).
+    /// Supports absolute uris, network-path (`//host/x`), path-absolute (`/x`), relative paths
+    /// (`../x`), query only (`?q`) and fragment only (`#f`) references.
+    /// Dot segments that are percent encoded, like `%2E%2E`, are not removed.
+    pub fn join(&self, reference: &str) -> Result<Uri<'static>> {
+        let scheme_end =
+            reference.find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)));
+        if scheme_end.is_some_and(|end| end > 0 && reference[end..].starts_with(':')) {
+            return Uri::parse(String::from(reference));
+        }
+
+        let (reference, fragment) = match reference.split_once('#') {
+            Some((reference, fragment)) => (reference, Some(fragment)),
+            None => (reference, None),
+        };
+
+        let mut target = String::from(self.scheme());
+        target.push_str("://");
+
+        if let Some(network_path) = reference.strip_prefix("//") {
+            let path_start = network_path.find(['/', '?']).unwrap_or(network_path.len());
+            target.push_str(&network_path[..path_start]);
+            let path = &network_path[path_start..];
+            if !path.starts_with('/') {
+                target.push('/');
+            }
+            target.push_str(path);
+        } else {
+            target.push_str(self.authority());
+
+            let (base_path, base_query) = (self.path(), self.query());
+            let (path, query) = match reference.split_once('?') {
+                Some((path, query)) => (path, Some(query)),
+                None => (reference, None),
+            };
+
+            if path.is_empty() {
+                target.push_str(base_path);
+            } else if path.starts_with('/') {
+                target.push_str(&remove_dot_segments(path));
+            } else {
+                // merge with the base path up to its last segment
+                let dir = &base_path[..base_path.rfind('/').map_or(0, |idx| idx + 1)];
+                target.push_str(&remove_dot_segments(&alloc::format!("{}{}", dir, path)));
+            }
+
+            if let Some(query) = query.or(base_query.filter(|_| path.is_empty())) {
+                target.push('?');
+                target.push_str(query);
+            }
+        }
+
+        if let Some(fragment) = fragment {
+            target.push('#');
+            target.push_str(fragment);
+        }
+
+        Uri::parse(target)
+    }
+
+    /// The host of the authority, without userinfo and port.
+    /// IP literals are returned without the surrounding brackets.
+    pub fn host(&self) -> &str {
+        split_host_port(self.authority()).map_or("", |(host, _)| host)
+    }
+
+    /// The port of the authority, if one is given
+    pub fn port(&self) -> Option<u16> {
+        match split_host_port(self.authority()) {
+            Ok((_, Some(port))) => port.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The port of the authority, or the default port of the scheme if none is given:
+    /// 80 for `http` and `ws`, 443 for `https` and `wss`, and `None` for other schemes
+    pub fn port_or_default(&self) -> Option<u16> {
+        self.port().or_else(|| {
+            let scheme = self.scheme();
+            if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("ws") {
+                Some(80)
+            } else if scheme.eq_ignore_ascii_case("https") || scheme.eq_ignore_ascii_case("wss") {
+                Some(443)
+            } else {
+                None
+            }
+        })
+    }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Uri<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.inner.as_ref());
+    }
+}
 
 impl<'a> TryFrom<Cow<'a, str>> for Uri<'a> {
     type Error = Error;
@@ -103,7 +418,6 @@ impl TryFrom<alloc::string::String> for Uri<'static> {
     }
 }
 
-
 impl<'a> TryFrom<&'a Uri<'a>> for Uri<'a> {
     type Error = Error;
 
@@ -135,6 +449,238 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_errors() {
+        let err = |uri| match Uri::parse(uri) {
+            Err(Error::InvalidUri(e)) => e,
+            res => panic!("{} parsed as {:?}", uri, res),
+        };
+
+        assert_eq!(err("test.com/asdf"), UriError::MissingScheme);
+        assert_eq!(err("://test.com/asdf"), UriError::MissingScheme);
+        assert_eq!(err("http:///asdf"), UriError::EmptyAuthority);
+        assert_eq!(err("http://"), UriError::EmptyAuthority);
+        assert_eq!(err("http://?x=1"), UriError::EmptyAuthority);
+        assert_eq!(err("http://test.com:http/"), UriError::InvalidPort);
+        assert_eq!(err("http://test.com:65536/"), UriError::InvalidPort);
+        assert_eq!(err("http://test.com/a%2"), UriError::InvalidPercentEncoding);
+        assert_eq!(
+            err("http://test.com/a%zz"),
+            UriError::InvalidPercentEncoding
+        );
+
+        assert!(Uri::parse("http://test.com:8080/a%20b").is_ok());
+        assert!(Uri::parse("http://[::1]:8080/").is_ok());
+        assert!(Uri::parse("http://user:pw@test.com/").is_ok());
+    }
+
+    #[test]
+    fn test_host_port() {
+        let uri = Uri::parse("http://[::1]/").unwrap();
+        assert_eq!(uri.authority(), "[::1]");
+        assert_eq!(uri.host(), "::1");
+        assert_eq!(uri.port(), None);
+
+        let uri = Uri::parse("http://[::1]:443/").unwrap();
+        assert_eq!(uri.host(), "::1");
+        assert_eq!(uri.port(), Some(443));
+
+        let uri = Uri::parse("http://user:pw@[2001:db8::1]:8080/a").unwrap();
+        assert_eq!(uri.host(), "2001:db8::1");
+        assert_eq!(uri.port(), Some(8080));
+
+        let uri = Uri::parse("http://192.168.1.5:8080/status").unwrap();
+        assert_eq!(uri.host(), "192.168.1.5");
+        assert_eq!(uri.port(), Some(8080));
+
+        let uri = Uri::parse("http://192.168.1.5?x=1").unwrap();
+        assert_eq!(uri.host(), "192.168.1.5");
+        assert_eq!(uri.port(), None);
+
+        let uri = Uri::parse("http://test.com:8080/").unwrap();
+        assert_eq!(uri.host(), "test.com");
+        assert_eq!(uri.port(), Some(8080));
+
+        let uri = Uri::parse("http://test.com:/").unwrap();
+        assert_eq!(uri.host(), "test.com");
+        assert_eq!(uri.port(), None);
+
+        for (uri, port) in [
+            ("http://test.com/", Some(80)),
+            ("HTTPS://test.com/", Some(443)),
+            ("ws://test.com/", Some(80)),
+            ("wss://[::1]/", Some(443)),
+            ("https://test.com:8443/", Some(8443)),
+            ("http://test.com:/", Some(80)),
+            ("mqtt://test.com/", None),
+        ] {
+            assert_eq!(Uri::parse(uri).unwrap().port_or_default(), port, "{}", uri);
+        }
+
+        for uri in ["http://[::1/", "http://[::1]8080/"] {
+            assert!(matches!(
+                Uri::parse(uri),
+                Err(Error::InvalidUri(UriError::InvalidHost))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_path_query() {
+        let uri = Uri::parse("http://test.com/asdf/1234?asdf=1234&b").unwrap();
+        assert_eq!(uri.path(), "/asdf/1234");
+        assert_eq!(uri.query(), Some("asdf=1234&b"));
+
+        let uri = Uri::parse("http://test.com/asdf/1234").unwrap();
+        assert_eq!(uri.path(), "/asdf/1234");
+        assert_eq!(uri.query(), None);
+
+        let uri = Uri::parse("http://test.com/?").unwrap();
+        assert_eq!(uri.path(), "/");
+        assert_eq!(uri.query(), Some(""));
+
+        let owned = uri.clone().into_owned();
+        assert_eq!(owned.query(), Some(""));
+    }
+
+    #[test]
+    fn test_empty_path() {
+        for (uri, normalized, path_and_query) in [
+            ("http://host?x=1", "http://host/?x=1", "/?x=1"),
+            ("http://host#top", "http://host/#top", "/"),
+            ("http://host:8080", "http://host:8080/", "/"),
+        ] {
+            let uri = Uri::parse(uri).unwrap();
+            assert_eq!(uri.as_str(), normalized);
+            assert_eq!(uri.authority(), normalized[7..].split('/').next().unwrap());
+            assert_eq!(uri.path(), "/");
+            assert_eq!(uri.path_and_query(), path_and_query);
+        }
+
+        let uri = Uri::parse("http://host?x=1").unwrap();
+        assert_eq!(uri.host(), "host");
+        assert_eq!(uri.query(), Some("x=1"));
+        assert_eq!(
+            Uri::parse("http://host#top").unwrap().fragment(),
+            Some("top")
+        );
+    }
+
+    #[test]
+    fn test_origin_form() {
+        let uri = Uri::parse_origin_form("/v1/health?verbose=1").unwrap();
+        assert_eq!(uri.scheme(), "");
+        assert_eq!(uri.authority(), "");
+        assert_eq!(uri.path_and_query(), "/v1/health?verbose=1");
+        assert_eq!(uri.path(), "/v1/health");
+        assert_eq!(uri.query(), Some("verbose=1"));
+        assert_eq!(uri.host(), "");
+        assert_eq!(uri.port(), None);
+
+        assert_eq!(Uri::parse_origin_form("/").unwrap().path_and_query(), "/");
+        for (target, err) in [
+            ("v1/health", UriError::InvalidOriginForm),
+            ("http://host/", UriError::InvalidOriginForm),
+            ("/a#top", UriError::InvalidOriginForm),
+            ("/a%2", UriError::InvalidPercentEncoding),
+        ] {
+            assert!(
+                matches!(Uri::parse_origin_form(target), Err(Error::InvalidUri(e)) if e == err),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_pairs() {
+        let uri =
+            Uri::parse("http://h/p?token=abc&ttl=60&flag&&msg=a+b%20c%21&k%3D=%E2%9C%93").unwrap();
+        let pairs: Vec<_> = uri.query_pairs().collect();
+        assert_eq!(
+            pairs,
+            [
+                ("token".into(), "abc".into()),
+                ("ttl".into(), "60".into()),
+                ("flag".into(), "".into()),
+                ("msg".into(), "a b c!".into()),
+                ("k=".into(), "\u{2713}".into()),
+            ]
+        );
+        assert!(matches!(pairs[0].1, Cow::Borrowed(_)));
+
+        assert_eq!(Uri::parse("http://h/p").unwrap().query_pairs().count(), 0);
+        assert_eq!(form_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn test_fragment() {
+        let uri = Uri::parse("http://h/p?q=1#frag").unwrap();
+        assert_eq!(uri.path(), "/p");
+        assert_eq!(uri.query(), Some("q=1"));
+        assert_eq!(uri.fragment(), Some("frag"));
+        assert_eq!(uri.path_and_query(), "/p?q=1");
+
+        let req = crate::request::RequestBuilder::get(uri)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(req
+            .to_vec()
+            .unwrap()
+            .starts_with(b"GET /p?q=1 HTTP/1.1\r\n"));
+
+        let uri = Uri::parse("http://h/p#a?b").unwrap();
+        assert_eq!(uri.path(), "/p");
+        assert_eq!(uri.query(), None);
+        assert_eq!(uri.fragment(), Some("a?b"));
+
+        let uri = Uri::parse("http://h/p").unwrap();
+        assert_eq!(uri.fragment(), None);
+        assert!(Uri::parse("http://h/p#%zz").is_err());
+    }
+
+    #[test]
+    fn test_join() {
+        // examples of RFC 3986 section 5.4
+        let base = Uri::parse("http://a/b/c/d;p?q").unwrap();
+        for (reference, target) in [
+            ("g:h", None),
+            ("https://h/x", Some("https://h/x")),
+            ("g", Some("http://a/b/c/g")),
+            ("./g", Some("http://a/b/c/g")),
+            ("g/", Some("http://a/b/c/g/")),
+            ("/g", Some("http://a/g")),
+            ("//g", Some("http://g/")),
+            ("?y", Some("http://a/b/c/d;p?y")),
+            ("g?y", Some("http://a/b/c/g?y")),
+            ("#s", Some("http://a/b/c/d;p?q#s")),
+            ("g?y#s", Some("http://a/b/c/g?y#s")),
+            ("", Some("http://a/b/c/d;p?q")),
+            (".", Some("http://a/b/c/")),
+            ("./", Some("http://a/b/c/")),
+            ("..", Some("http://a/b/")),
+            ("../g", Some("http://a/b/g")),
+            ("../..", Some("http://a/")),
+            ("../../g", Some("http://a/g")),
+            ("../../../g", Some("http://a/g")),
+            ("/./g", Some("http://a/g")),
+            ("/../g", Some("http://a/g")),
+            ("g.", Some("http://a/b/c/g.")),
+            ("..g", Some("http://a/b/c/..g")),
+            ("./../g", Some("http://a/b/g")),
+            ("g/./h", Some("http://a/b/c/g/h")),
+            ("g/../h", Some("http://a/b/c/h")),
+        ] {
+            let joined = base.join(reference);
+            match target {
+                Some(target) => assert_eq!(joined.unwrap().as_str(), target, "{}", reference),
+                // not a uri with an authority, which is all `Uri` supports
+                None => assert!(joined.is_err(), "{}", reference),
+            }
+        }
+    }
+
     #[test]
     fn test_into_owned() {
         let uri = Uri::parse("https://www.google.com/").unwrap();
@@ -147,4 +693,3 @@ mod tests {
         assert_eq!(uri3, uri);
     }
 }
-
@@ -0,0 +1,3 @@
+//! Small encoders shared by the request helpers, usable without an allocator.
+
+pub mod base64;
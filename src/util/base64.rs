@@ -0,0 +1,136 @@
+//! Base64 with the standard alphabet and padding (RFC 4648 section 4), as used by
+//! `Authorization: Basic` and the WebSocket handshake.
+
+use embedded_io::{ErrorType, Write};
+
+use crate::{Error, Result};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Length of `len` bytes once encoded, padding included
+pub const fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Writes `input` encoded, with padding
+pub fn encode_into<W: Write>(input: &[u8], out: &mut W) -> Result<()>
+where
+    Error: From<<W as ErrorType>::Error>,
+{
+    for chunk in input.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+
+        let mut quad = [b'='; 4];
+        for (i, o) in quad.iter_mut().enumerate().take(chunk.len() + 1) {
+            *o = ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f];
+        }
+        out.write_all(&quad)?;
+    }
+    Ok(())
+}
+
+/// Decodes `input` into `out` and returns the number of bytes decoded.
+/// Padding is optional. Fails with `Error::InvalidBase64` for characters outside the alphabet
+/// or a truncated input, and with `Error::BufferTooSmall` if `out` can't hold the result.
+pub fn decode_into(input: &str, out: &mut [u8]) -> Result<usize> {
+    let input = input.as_bytes();
+    let data = input
+        .strip_suffix(b"==")
+        .or_else(|| input.strip_suffix(b"="));
+    let data = match data {
+        Some(_) if !input.len().is_multiple_of(4) => return Err(Error::InvalidBase64),
+        Some(data) => data,
+        None => input,
+    };
+    if data.len() % 4 == 1 {
+        return Err(Error::InvalidBase64);
+    }
+
+    let need = data.len() * 3 / 4;
+    if need > out.len() {
+        return Err(Error::BufferTooSmall(out.len(), need));
+    }
+
+    let mut len = 0;
+    for chunk in data.chunks(4) {
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            n |= u32::from(sextet(c)?) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out[len] = (n >> (16 - 8 * i)) as u8;
+            len += 1;
+        }
+    }
+    Ok(len)
+}
+
+fn sextet(c: u8) -> Result<u8> {
+    Ok(match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return Err(Error::InvalidBase64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::SliceWriter;
+
+    // RFC 4648 section 10
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn rfc4648_vectors() {
+        for (plain, encoded) in VECTORS {
+            let mut buf = [0; 8];
+            let mut w = SliceWriter::new(&mut buf);
+            encode_into(plain.as_bytes(), &mut w).unwrap();
+            assert_eq!(w.as_slice(), encoded.as_bytes());
+            assert_eq!(encoded_len(plain.len()), encoded.len());
+
+            let mut out = [0; 6];
+            let len = decode_into(encoded, &mut out).unwrap();
+            assert_eq!(&out[..len], plain.as_bytes());
+
+            let len = decode_into(encoded.trim_end_matches('='), &mut out).unwrap();
+            assert_eq!(&out[..len], plain.as_bytes());
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut out = [0; 6];
+        assert!(matches!(
+            decode_into("Zm9v!A==", &mut out),
+            Err(Error::InvalidBase64)
+        ));
+        assert!(matches!(
+            decode_into("Zm9vY", &mut out),
+            Err(Error::InvalidBase64)
+        ));
+        assert!(matches!(
+            decode_into("Zg=", &mut out),
+            Err(Error::InvalidBase64)
+        ));
+        assert!(matches!(
+            decode_into("Zm9vYmFy", &mut out[..5]),
+            Err(Error::BufferTooSmall(5, 6))
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers for the WebSocket opening handshake (RFC 6455).
+
+use crate::util::base64;
+use crate::writer::SliceWriter;
+
+/// GUID appended to the `Sec-WebSocket-Key` when computing the accept value
+const MAGIC_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Length of a base64 encoded SHA-1 digest
+const ACCEPT_LEN: usize = base64::encoded_len(20);
+
+/// Computes the `Sec-WebSocket-Accept` value the server must answer with for the given key
+fn accept_for(key: &str) -> [u8; ACCEPT_LEN] {
+    let mut sha1 = sha1_smol::Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(MAGIC_GUID);
+    let digest = sha1.digest().bytes();
+
+    let mut out = [0; ACCEPT_LEN];
+    // the digest always fits
+    let _ = base64::encode_into(&digest, &mut SliceWriter::new(&mut out));
+    out
+}
+
+/// Validates the `Sec-WebSocket-Accept` header returned by the server against the key that was sent
+pub fn verify_accept(key: &str, accept: &str) -> bool {
+    accept.trim().as_bytes() == accept_for(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc6455_vector() {
+        assert!(verify_accept(
+            "dGhlIHNhbXBsZSBub25jZQ==",
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        ));
+        assert!(!verify_accept(
+            "dGhlIHNhbXBsZSBub25jZQ==",
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOp="
+        ));
+        assert!(!verify_accept("dGhlIHNhbXBsZSBub25jZQ==", ""));
+    }
+
+    #[test]
+    fn handshake_response() {
+        let mut resp = crate::response::Response::new(
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n",
+        );
+        assert!(resp.is_switching_protocols().unwrap());
+        assert!(verify_accept(
+            "dGhlIHNhbXBsZSBub25jZQ==",
+            resp.sec_websocket_accept().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+//! Writers usable as the target of `write_to` without an allocator.
+
+use embedded_io::{ErrorType, Write};
+
+use crate::{Error, Result};
+
+/// Formats integers for header values like `Content-Length` without allocating.
+/// Uses `itoa` if the `itoa` feature is enabled and `core::fmt` otherwise, with the same output.
+#[cfg(feature = "itoa")]
+pub(crate) struct IntBuffer(itoa::Buffer);
+
+#[cfg(feature = "itoa")]
+impl IntBuffer {
+    pub(crate) fn new() -> Self {
+        Self(itoa::Buffer::new())
+    }
+
+    pub(crate) fn format<I: itoa::Integer>(&mut self, i: I) -> &str {
+        self.0.format(i)
+    }
+}
+
+/// Formats integers for header values like `Content-Length` without allocating.
+/// Uses `itoa` if the `itoa` feature is enabled and `core::fmt` otherwise, with the same output.
+#[cfg(not(feature = "itoa"))]
+pub(crate) struct IntBuffer {
+    /// fits `i128::MIN`
+    buf: [u8; 40],
+    len: usize,
+}
+
+#[cfg(not(feature = "itoa"))]
+impl IntBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: [0; 40],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn format<I: core::fmt::Display>(&mut self, i: I) -> &str {
+        use core::fmt::Write;
+
+        self.len = 0;
+        // every integer fits the buffer
+        let _ = write!(self, "{}", i);
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+#[cfg(not(feature = "itoa"))]
+impl core::fmt::Write for IntBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(core::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Writes into a fixed size byte slice.
+///
+/// Writes that don't fit fail with [`Error::BufferTooSmall`] and leave the buffer unchanged.
+/// Since `write!` reduces that error to a generic one, the last overflow is also kept in the
+/// writer and can be recovered with [`SliceWriter::take_error`].
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    error: Option<Error>,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            len: 0,
+            error: None,
+        }
+    }
+
+    /// Number of bytes written
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written so far
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Appends all of `bytes`, or nothing if they don't fit
+    pub fn extend(&mut self, bytes: &[u8]) -> Result<()> {
+        let need = self.len + bytes.len();
+        if need > self.buf.len() {
+            self.error = Some(Error::BufferTooSmall(self.buf.len(), need));
+            return Err(Error::BufferTooSmall(self.buf.len(), need));
+        }
+
+        self.buf[self.len..need].copy_from_slice(bytes);
+        self.len = need;
+        Ok(())
+    }
+
+    /// Appends as much of `bytes` as fits and returns how many bytes were written,
+    /// e.g. to fill the buffer with a large body, drain it with [`SliceWriter::clear`] and continue
+    pub fn write_partial(&mut self, bytes: &[u8]) -> usize {
+        let n = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+        n
+    }
+
+    /// Forgets the bytes written, making the whole buffer available again
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Takes the error of the last write that didn't fit, if any
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl ErrorType for SliceWriter<'_> {
+    type Error = Error;
+}
+
+impl Write for SliceWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl core::fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.extend(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Discards everything written to it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sink;
+
+impl ErrorType for Sink {
+    type Error = core::convert::Infallible;
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Passes writes through to the inner writer, counting the bytes written.
+/// The default writes into a [`Sink`], to measure the size of e.g. a request without a buffer.
+#[derive(Debug)]
+pub struct CountingWriter<W = Sink> {
+    inner: W,
+    count: usize,
+}
+
+impl Default for CountingWriter {
+    fn default() -> Self {
+        Self::new(Sink)
+    }
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Number of bytes written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> ErrorType for CountingWriter<W> {
+    type Error = W::Error;
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, W::Error> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), W::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Collects small writes in a buffer of `N` bytes and passes them on in larger pieces,
+/// e.g. to send the header of a request in a few writes instead of one per line over TLS.
+/// Writes at least as large as the buffer go straight through.
+///
+/// Call [`Write::flush`] after writing the request, or the tail stays in the buffer.
+#[derive(Debug)]
+pub struct BufWriter<W, const N: usize = 512> {
+    inner: W,
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<W: Write, const N: usize> BufWriter<W, N> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// The bytes buffered and not written to the inner writer yet
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Flushes the buffer and returns the inner writer
+    pub fn into_inner(mut self) -> core::result::Result<W, W::Error> {
+        self.flush_buf()?;
+        Ok(self.inner)
+    }
+
+    fn flush_buf(&mut self) -> core::result::Result<(), W::Error> {
+        self.inner.write_all(&self.buf[..self.len])?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write, const N: usize> ErrorType for BufWriter<W, N> {
+    type Error = W::Error;
+}
+
+impl<W: Write, const N: usize> Write for BufWriter<W, N> {
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, W::Error> {
+        if self.len + buf.len() > N {
+            self.flush_buf()?;
+        }
+        if buf.len() >= N {
+            return self.inner.write(buf);
+        }
+
+        self.buf[self.len..self.len + buf.len()].copy_from_slice(buf);
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), W::Error> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+/// Forwards every write to both writers, e.g. to mirror a request sent over a socket to a log.
+/// The first error of either writer aborts the write.
+#[derive(Debug)]
+pub struct TeeWriter<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: Write, B: Write> TeeWriter<A, B>
+where
+    Error: From<A::Error> + From<B::Error>,
+{
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+impl<A: Write, B: Write> ErrorType for TeeWriter<A, B>
+where
+    Error: From<A::Error> + From<B::Error>,
+{
+    type Error = Error;
+}
+
+impl<A: Write, B: Write> Write for TeeWriter<A, B>
+where
+    Error: From<A::Error> + From<B::Error>,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        // `b` gets exactly what `a` accepted
+        let written = self.a.write(buf)?;
+        self.b.write_all(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.a.flush()?;
+        self.b.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestBuilder;
+
+    #[test]
+    fn int_buffer() {
+        let mut b = IntBuffer::new();
+        assert_eq!(b.format(0usize), "0");
+        assert_eq!(b.format(1234usize), "1234");
+        assert_eq!(
+            b.format(i128::MIN),
+            "-170141183460469231731687303715884105728"
+        );
+        assert_eq!(b.format(u64::MAX), "18446744073709551615");
+    }
+
+    #[test]
+    fn write_request() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("hei")
+            .unwrap();
+
+        let mut buf = [0; 256];
+        let mut w = SliceWriter::new(&mut buf);
+        req.write_to(&mut w).unwrap();
+        assert_eq!(w.as_slice(), req.to_vec().unwrap());
+        assert!(w.take_error().is_none());
+    }
+
+    #[test]
+    fn counting_writer() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("hei")
+            .unwrap();
+
+        let mut counter = CountingWriter::default();
+        req.write_to(&mut counter).unwrap();
+        assert_eq!(counter.count(), req.to_vec().unwrap().len());
+
+        let mut buf = [0; 256];
+        let mut counter = CountingWriter::new(SliceWriter::new(&mut buf));
+        req.write_to(&mut counter).unwrap();
+        assert_eq!(counter.count(), counter.into_inner().len());
+    }
+
+    #[test]
+    fn write_partial() {
+        let body = b"0123456789abcdefghij";
+        let mut buf = [0; 8];
+        let mut w = SliceWriter::new(&mut buf);
+        let mut drained = alloc::vec::Vec::new();
+
+        let mut rest = &body[..];
+        while !rest.is_empty() {
+            let n = w.write_partial(rest);
+            rest = &rest[n..];
+            drained.extend_from_slice(w.as_slice());
+            w.clear();
+        }
+        assert_eq!(drained, body);
+
+        assert_eq!(w.write_partial(b"0123"), 4);
+        assert_eq!(w.write_partial(b"456789"), 4);
+        assert_eq!(w.write_partial(b"x"), 0);
+        assert_eq!(w.as_slice(), b"01234567");
+        assert!(w.take_error().is_none());
+    }
+
+    #[test]
+    fn buf_writer() {
+        /// Records the size of every write
+        #[derive(Default)]
+        struct Writes(alloc::vec::Vec<usize>, alloc::vec::Vec<u8>);
+
+        impl ErrorType for Writes {
+            type Error = core::convert::Infallible;
+        }
+
+        impl Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+                self.0.push(buf.len());
+                self.1.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .insert_header(("x-token".into(), "abc".into()))
+            .body("hei")
+            .unwrap();
+
+        let mut unbuffered = Writes::default();
+        req.write_to(&mut unbuffered).unwrap();
+
+        let mut w = BufWriter::<_, 64>::new(Writes::default());
+        req.write_to(&mut w).unwrap();
+        w.flush().unwrap();
+        assert!(w.buffer().is_empty());
+        let writes = w.into_inner().unwrap();
+        assert_eq!(writes.1, unbuffered.1);
+        assert!(writes.0.len() < unbuffered.0.len() / 2, "{:?}", writes.0);
+        assert!(writes.0.iter().all(|&n| n <= 64));
+
+        // large writes bypass the buffer, after flushing what came before
+        let mut w = BufWriter::<_, 4>::new(Writes::default());
+        w.write_all(b"ab").unwrap();
+        w.write_all(b"cdefgh").unwrap();
+        w.write_all(b"i").unwrap();
+        assert_eq!(w.buffer(), b"i");
+        assert_eq!(w.get_ref().0, [2, 6]);
+        assert_eq!(w.into_inner().unwrap().1, b"abcdefghi");
+    }
+
+    #[test]
+    fn tee_writer() {
+        let req = RequestBuilder::post("https://google.com/")
+            .unwrap()
+            .body("hei")
+            .unwrap();
+
+        let mut buf = [0; 256];
+        let mut tee = TeeWriter::new(SliceWriter::new(&mut buf), alloc::vec::Vec::new());
+        req.write_to(&mut tee).unwrap();
+        let (socket, log) = tee.into_inner();
+        assert_eq!(socket.as_slice(), log);
+        assert_eq!(log, req.to_vec().unwrap());
+
+        let mut small = [0; 16];
+        let mut tee = TeeWriter::new(alloc::vec::Vec::new(), SliceWriter::new(&mut small));
+        assert!(req.write_to(&mut tee).is_err());
+        let (_, mut log) = tee.into_inner();
+        assert!(matches!(
+            log.take_error(),
+            Some(Error::BufferTooSmall(16, _))
+        ));
+    }
+
+    #[test]
+    fn overflow_detail() {
+        let req = RequestBuilder::get("https://google.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut buf = [0; 16];
+        let mut w = SliceWriter::new(&mut buf);
+        assert!(req.write_to(&mut w).is_err());
+        assert!(matches!(w.take_error(), Some(Error::BufferTooSmall(16, need)) if need > 16));
+        assert!(w.take_error().is_none());
+
+        let mut w = SliceWriter::new(&mut buf);
+        assert!(core::fmt::Write::write_str(&mut w, "0123456789abcdefg").is_err());
+        assert!(w.is_empty());
+        assert!(matches!(
+            w.take_error(),
+            Some(Error::BufferTooSmall(16, 17))
+        ));
+    }
+}